@@ -4,10 +4,16 @@
 //
 use crate::rpc;
 use anyhow::{anyhow, bail, ensure, Context, Result};
-use serde::Deserialize;
+#[cfg(feature = "guest-pull")]
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
 use std::env;
 use std::fs;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::Path;
 use std::str::FromStr;
+use std::sync::{Arc, RwLock};
 use std::time;
 use strum_macros::{Display, EnumString};
 use tracing::instrument;
@@ -28,6 +34,9 @@ const LOG_VPORT_OPTION: &str = "agent.log_vport";
 const CONTAINER_PIPE_SIZE_OPTION: &str = "agent.container_pipe_size";
 const UNIFIED_CGROUP_HIERARCHY_OPTION: &str = "systemd.unified_cgroup_hierarchy";
 const CONFIG_FILE: &str = "agent.config_file";
+const SERVER_RETRY_MAX_ATTEMPTS_OPTION: &str = "agent.server_retry_max_attempts";
+const SERVER_RETRY_INITIAL_INTERVAL_OPTION: &str = "agent.server_retry_initial_interval";
+const SERVER_RETRY_MAX_INTERVAL_OPTION: &str = "agent.server_retry_max_interval";
 const GUEST_COMPONENTS_REST_API_OPTION: &str = "agent.guest_components_rest_api";
 const GUEST_COMPONENTS_PROCS_OPTION: &str = "agent.guest_components_procs";
 #[cfg(feature = "guest-pull")]
@@ -40,6 +49,18 @@ const ENABLE_SIGNATURE_VERIFICATION: &str = "agent.enable_signature_verification
 #[cfg(feature = "guest-pull")]
 const IMAGE_POLICY_FILE: &str = "agent.image_policy_file";
 
+#[cfg(feature = "guest-pull")]
+const IMAGE_POLICY_OPTION: &str = "agent.image_policy";
+
+const CONFIG_VERSION_OPTION: &str = "agent.config_version";
+const DUMP_CONFIG_OPTION: &str = "agent.dump_config";
+const IMMEDIATE_SHUTDOWN_OPTION: &str = "agent.immediate_shutdown";
+const CONFIG_STRICT_OPTION: &str = "agent.config_strict";
+
+// CLI arguments for strict configuration validation and its output format.
+const CONFIG_CHECK_ARG: &str = "--config-check";
+const FORMAT_ARG: &str = "--format";
+
 // Configure the proxy settings for HTTPS requests in the guest,
 // to solve the problem of not being able to access the specified image in some cases.
 const HTTPS_PROXY: &str = "agent.https_proxy";
@@ -49,12 +70,36 @@ const DEFAULT_LOG_LEVEL: slog::Level = slog::Level::Info;
 const DEFAULT_HOTPLUG_TIMEOUT: time::Duration = time::Duration::from_secs(3);
 const DEFAULT_CDH_API_TIMEOUT: time::Duration = time::Duration::from_secs(50);
 const DEFAULT_CONTAINER_PIPE_SIZE: i32 = 0;
+// The config schema version this agent natively speaks. The host and guest use
+// it as a handshake: an option is only honoured when `config_version` is at or
+// above the version that introduced it, so an older host talking to a newer
+// agent (or vice versa) degrades gracefully instead of failing.
+const DEFAULT_CONFIG_VERSION: u16 = 2;
+// `cdh_api_timeout` and the guest-components selectors were added in v2; v1
+// hosts never set them, so a v1 agent ignores them and keeps the defaults.
+const CONFIG_VERSION_CDH_API_TIMEOUT: u16 = 2;
+const CONFIG_VERSION_GUEST_COMPONENTS: u16 = 2;
+// Server reconnect backoff: by default retry forever, starting at 1s and
+// doubling up to a 30s ceiling.
+const DEFAULT_SERVER_RETRY_MAX_ATTEMPTS: u32 = 0;
+const DEFAULT_SERVER_RETRY_INITIAL_INTERVAL: time::Duration = time::Duration::from_secs(1);
+const DEFAULT_SERVER_RETRY_MAX_INTERVAL: time::Duration = time::Duration::from_secs(30);
 const VSOCK_ADDR: &str = "vsock://-1";
 
 // Environment variables used for development and testing
 const SERVER_ADDR_ENV_VAR: &str = "KATA_AGENT_SERVER_ADDR";
+const CONFIG_VERSION_ENV_VAR: &str = "KATA_AGENT_CONFIG_VERSION";
 const LOG_LEVEL_ENV_VAR: &str = "KATA_AGENT_LOG_LEVEL";
 const TRACING_ENV_VAR: &str = "KATA_AGENT_TRACING";
+const DUMP_CONFIG_ENV_VAR: &str = "KATA_AGENT_DUMP_CONFIG";
+const IMMEDIATE_SHUTDOWN_ENV_VAR: &str = "KATA_AGENT_IMMEDIATE_SHUTDOWN";
+const SERVER_RETRY_MAX_ATTEMPTS_ENV_VAR: &str = "KATA_AGENT_SERVER_RETRY_MAX_ATTEMPTS";
+const SERVER_RETRY_INITIAL_INTERVAL_ENV_VAR: &str = "KATA_AGENT_SERVER_RETRY_INITIAL_INTERVAL";
+const SERVER_RETRY_MAX_INTERVAL_ENV_VAR: &str = "KATA_AGENT_SERVER_RETRY_MAX_INTERVAL";
+
+// CLI argument that forces the agent to dump its resolved configuration and
+// exit without starting any server.
+const DUMP_CONFIG_ARG: &str = "--dump-config";
 
 const ERR_INVALID_LOG_LEVEL: &str = "invalid log level";
 const ERR_INVALID_LOG_LEVEL_PARAM: &str = "invalid log level parameter";
@@ -71,9 +116,20 @@ const ERR_INVALID_CONTAINER_PIPE_SIZE_PARAM: &str = "unable to parse container p
 const ERR_INVALID_CONTAINER_PIPE_SIZE_KEY: &str = "invalid container pipe size key name";
 const ERR_INVALID_CONTAINER_PIPE_NEGATIVE: &str = "container pipe size should not be negative";
 
+const ERR_INVALID_SERVER_RETRY: &str = "invalid server retry parameter";
+const ERR_INVALID_SERVER_RETRY_PARAM: &str = "unable to parse server retry value";
+const ERR_INVALID_SERVER_RETRY_KEY: &str = "invalid server retry key name";
+
+const ERR_VSOCK_PORT_COLLISION: &str = "vsock ports must be distinct";
+const ERR_SERVER_ADDR_PARSE: &str = "unable to parse server_addr";
+const ERR_REST_API_WITHOUT_SERVER: &str = "guest_components_rest_api requires guest_components_procs=api-server-rest to spawn the REST server";
+
 const ERR_INVALID_GUEST_COMPONENTS_REST_API_VALUE: &str = "invalid guest components rest api feature given. Valid values are `all`, `attestation`, `resource`";
 const ERR_INVALID_GUEST_COMPONENTS_PROCS_VALUE: &str = "invalid guest components process param given. Valid values are `attestation-agent`, `confidential-data-hub`, `api-server-rest`, or `none`";
 
+#[cfg(feature = "guest-pull")]
+const ERR_IMAGE_POLICY_EXPIRED: &str = "image policy has expired";
+
 #[derive(Clone, Copy, Debug, Default, Display, Deserialize, EnumString, PartialEq)]
 // Features seem to typically be in kebab-case format, but we only have single words at the moment
 #[strum(serialize_all = "kebab-case")]
@@ -99,21 +155,233 @@ pub enum GuestComponentsProcs {
     ConfidentialDataHub,
 }
 
+/// A single constraint a pulled image must satisfy, modelled on the conditions
+/// of an S3 browser-based POST policy.
+#[cfg(feature = "guest-pull")]
+#[derive(Clone, Debug, PartialEq)]
+pub enum PolicyCondition {
+    /// `["eq", "$field", "value"]` — the field must equal `value` exactly.
+    Eq { field: String, value: String },
+    /// `["starts-with", "$field", "prefix"]` — the field must start with `prefix`.
+    StartsWith { field: String, value: String },
+    /// `["content-length-range", min, max]` — a numeric field must fall within
+    /// the inclusive range.
+    ContentLengthRange { min: u64, max: u64 },
+}
+
+#[cfg(feature = "guest-pull")]
+impl PolicyCondition {
+    // Convert one raw JSON array into a typed condition, rejecting unknown
+    // verbs and malformed shapes with a specific error.
+    fn from_value(value: serde_json::Value) -> Result<Self> {
+        let items = value
+            .as_array()
+            .ok_or_else(|| anyhow!("image policy condition must be a JSON array"))?;
+        let verb = items
+            .first()
+            .and_then(serde_json::Value::as_str)
+            .ok_or_else(|| anyhow!("image policy condition missing verb"))?;
+
+        match verb {
+            "eq" | "starts-with" => {
+                ensure!(
+                    items.len() == 3,
+                    "image policy `{}` condition expects 2 operands",
+                    verb
+                );
+                let field = items[1]
+                    .as_str()
+                    .ok_or_else(|| anyhow!("image policy condition field must be a string"))?
+                    .to_string();
+                let value = items[2]
+                    .as_str()
+                    .ok_or_else(|| anyhow!("image policy condition value must be a string"))?
+                    .to_string();
+                if verb == "eq" {
+                    Ok(PolicyCondition::Eq { field, value })
+                } else {
+                    Ok(PolicyCondition::StartsWith { field, value })
+                }
+            }
+            "content-length-range" => {
+                ensure!(
+                    items.len() == 3,
+                    "image policy `content-length-range` condition expects a min and max"
+                );
+                let min = items[1]
+                    .as_u64()
+                    .ok_or_else(|| anyhow!("content-length-range min must be a number"))?;
+                let max = items[2]
+                    .as_u64()
+                    .ok_or_else(|| anyhow!("content-length-range max must be a number"))?;
+                ensure!(min <= max, "content-length-range min must not exceed max");
+                Ok(PolicyCondition::ContentLengthRange { min, max })
+            }
+            other => bail!("unknown image policy condition verb: {}", other),
+        }
+    }
+}
+
+// Wire representation of an image policy document as it arrives on the cmdline,
+// before the heterogeneous condition arrays are validated.
+#[cfg(feature = "guest-pull")]
+#[derive(Debug, Deserialize)]
+struct ImagePolicyDocument {
+    expiration: i64,
+    conditions: Vec<serde_json::Value>,
+}
+
+/// A base64-encoded, S3-POST-style image-pull policy carrying an expiration and
+/// a set of conditions each pulled image's metadata must satisfy.
+#[cfg(feature = "guest-pull")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImagePolicy {
+    /// Unix timestamp (seconds since the epoch) after which the policy is
+    /// rejected.
+    pub expiration: i64,
+    /// Conditions applied to image metadata, in document order.
+    pub conditions: Vec<PolicyCondition>,
+}
+
+#[cfg(feature = "guest-pull")]
+impl ImagePolicy {
+    // Decode a base64 document, parse its JSON, validate every condition verb
+    // and reject a policy that has already expired.
+    fn from_base64(encoded: &str) -> Result<Self> {
+        let raw = base64::engine::general_purpose::STANDARD
+            .decode(encoded.trim())
+            .context("unable to base64-decode image policy")?;
+        let doc: ImagePolicyDocument =
+            serde_json::from_slice(&raw).context("unable to parse image policy document")?;
+
+        let conditions = doc
+            .conditions
+            .into_iter()
+            .map(PolicyCondition::from_value)
+            .collect::<Result<Vec<_>>>()?;
+
+        let policy = ImagePolicy {
+            expiration: doc.expiration,
+            conditions,
+        };
+        policy.ensure_not_expired()?;
+
+        Ok(policy)
+    }
+
+    // Reject the policy if its expiration is in the past relative to the
+    // current wall-clock time. The comparison is signed so a negative (or zero)
+    // timestamp is rejected outright rather than wrapping to a huge `u64` and
+    // masquerading as "far in the future".
+    fn ensure_not_expired(&self) -> Result<()> {
+        let now = time::SystemTime::now()
+            .duration_since(time::UNIX_EPOCH)
+            .context("unable to read current time")?
+            .as_secs() as i64;
+        ensure!(
+            self.expiration > 0 && self.expiration >= now,
+            ERR_IMAGE_POLICY_EXPIRED
+        );
+        Ok(())
+    }
+}
+
+/// Where a configuration value that failed validation originated.
+///
+/// Only the cmdline and the environment are scanned. Config-file values are
+/// not diagnosed: a value of the wrong type is rejected by the deserializer
+/// (`from_str_with_format`), but an invalid `log_level` token in a config file
+/// is dropped without any diagnostic.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticSource {
+    Cmdline,
+    Env,
+}
+
+/// A single machine-readable configuration diagnostic, emitted by the strict
+/// validation mode for tooling to consume.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ConfigDiagnostic {
+    /// The offending key (e.g. `agent.container_pipe_size`).
+    pub key: String,
+    /// The raw, unparsed value as supplied.
+    pub value: String,
+    /// A human-readable explanation of why the value was rejected.
+    pub reason: String,
+    /// The source the key came from.
+    pub source: DiagnosticSource,
+}
+
+// The complete set of recognized `agent.*` cmdline keys, used to flag typo'd
+// or unknown keys in strict mode.
+const KNOWN_CMDLINE_KEYS: &[&str] = &[
+    DEBUG_CONSOLE_FLAG,
+    DEV_MODE_FLAG,
+    TRACE_MODE_OPTION,
+    LOG_LEVEL_OPTION,
+    CONFIG_VERSION_OPTION,
+    SERVER_ADDR_OPTION,
+    SERVER_RETRY_MAX_ATTEMPTS_OPTION,
+    SERVER_RETRY_INITIAL_INTERVAL_OPTION,
+    SERVER_RETRY_MAX_INTERVAL_OPTION,
+    PASSFD_LISTENER_PORT,
+    HOTPLUG_TIMOUT_OPTION,
+    CDH_API_TIMOUT_OPTION,
+    DEBUG_CONSOLE_VPORT_OPTION,
+    LOG_VPORT_OPTION,
+    CONTAINER_PIPE_SIZE_OPTION,
+    UNIFIED_CGROUP_HIERARCHY_OPTION,
+    CONFIG_FILE,
+    GUEST_COMPONENTS_REST_API_OPTION,
+    GUEST_COMPONENTS_PROCS_OPTION,
+    SECURE_STORAGE_INTEGRITY_OPTION,
+    HTTPS_PROXY,
+    NO_PROXY,
+    DUMP_CONFIG_OPTION,
+    IMMEDIATE_SHUTDOWN_OPTION,
+    CONFIG_STRICT_OPTION,
+    #[cfg(feature = "guest-pull")]
+    IMAGE_REGISTRY_AUTH_OPTION,
+    #[cfg(feature = "guest-pull")]
+    ENABLE_SIGNATURE_VERIFICATION,
+    #[cfg(feature = "guest-pull")]
+    IMAGE_POLICY_FILE,
+    #[cfg(feature = "guest-pull")]
+    IMAGE_POLICY_OPTION,
+];
+
 #[derive(Debug)]
 pub struct AgentConfig {
+    /// Negotiated config schema version. Capability probes such as
+    /// [`supports_cdh_api_timeout`](AgentConfig::supports_cdh_api_timeout) are
+    /// gated on it.
+    pub config_version: u16,
     pub debug_console: bool,
     pub dev_mode: bool,
     pub log_level: slog::Level,
+    /// Per-target log-level overrides parsed from `agent.log`, sorted by
+    /// descending target-prefix length so the most specific rule wins.
+    pub log_level_rules: Vec<(String, slog::Level)>,
     pub hotplug_timeout: time::Duration,
     pub cdh_api_timeout: time::Duration,
     pub debug_console_vport: i32,
     pub log_vport: i32,
     pub container_pipe_size: i32,
     pub server_addr: String,
+    /// Maximum number of agent→runtime reconnect attempts (`0` = retry forever).
+    pub server_retry_max_attempts: u32,
+    /// Initial backoff interval between reconnect attempts.
+    pub server_retry_initial_interval: time::Duration,
+    /// Upper bound the backoff interval doubles towards.
+    pub server_retry_max_interval: time::Duration,
     pub passfd_listener_port: i32,
     pub unified_cgroup_hierarchy: bool,
     pub tracing: bool,
     pub supports_seccomp: bool,
+    pub dump_config: bool,
+    pub immediate_shutdown: bool,
+    pub config_strict: bool,
     pub https_proxy: String,
     pub no_proxy: String,
     pub guest_components_rest_api: GuestComponentsFeatures,
@@ -125,22 +393,59 @@ pub struct AgentConfig {
     pub enable_signature_verification: bool,
     #[cfg(feature = "guest-pull")]
     pub image_policy_file: String,
+    #[cfg(feature = "guest-pull")]
+    pub image_policy: Option<ImagePolicy>,
+}
+
+// Deserialize an optional `time::Duration` from a bare integer number of
+// seconds, matching how durations are written on the cmdline and emitted by
+// `to_toml` (serde's default representation is a `{secs, nanos}` struct, which
+// a hand-written config file would never use).
+mod duration_secs_opt {
+    use super::time;
+    use serde::{Deserialize, Deserializer};
+
+    pub(super) fn deserialize<'de, D>(deserializer: D) -> Result<Option<time::Duration>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let secs = Option::<u64>::deserialize(deserializer)?;
+        Ok(secs.map(time::Duration::from_secs))
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct AgentConfigBuilder {
+    pub config_version: Option<u16>,
     pub debug_console: Option<bool>,
     pub dev_mode: Option<bool>,
+    /// Raw `agent.log`-style directive list (e.g. `info,rustjail=debug`). The
+    /// bare default level is extracted from it on apply; a config file may set
+    /// it to a single level for backwards compatibility.
     pub log_level: Option<String>,
+    /// Per-target rules parsed from the directive list. Populated by the
+    /// cmdline/env sources so they survive the layered [`resolve`] pipeline;
+    /// never deserialized from a config file directly.
+    #[serde(skip)]
+    pub log_level_rules: Option<Vec<(String, slog::Level)>>,
+    #[serde(default, deserialize_with = "duration_secs_opt::deserialize")]
     pub hotplug_timeout: Option<time::Duration>,
+    #[serde(default, deserialize_with = "duration_secs_opt::deserialize")]
     pub cdh_api_timeout: Option<time::Duration>,
     pub debug_console_vport: Option<i32>,
     pub log_vport: Option<i32>,
     pub container_pipe_size: Option<i32>,
     pub server_addr: Option<String>,
+    pub server_retry_max_attempts: Option<u32>,
+    #[serde(default, deserialize_with = "duration_secs_opt::deserialize")]
+    pub server_retry_initial_interval: Option<time::Duration>,
+    #[serde(default, deserialize_with = "duration_secs_opt::deserialize")]
+    pub server_retry_max_interval: Option<time::Duration>,
     pub passfd_listener_port: Option<i32>,
     pub unified_cgroup_hierarchy: Option<bool>,
     pub tracing: Option<bool>,
+    pub dump_config: Option<bool>,
+    pub immediate_shutdown: Option<bool>,
     pub https_proxy: Option<String>,
     pub no_proxy: Option<String>,
     pub guest_components_rest_api: Option<GuestComponentsFeatures>,
@@ -154,6 +459,98 @@ pub struct AgentConfigBuilder {
     pub image_policy_file: Option<String>,
 }
 
+impl AgentConfigBuilder {
+    // An all-`None` builder, used as the accumulator when layering several
+    // configuration sources together.
+    fn empty() -> Self {
+        AgentConfigBuilder {
+            config_version: None,
+            debug_console: None,
+            dev_mode: None,
+            log_level: None,
+            log_level_rules: None,
+            hotplug_timeout: None,
+            cdh_api_timeout: None,
+            debug_console_vport: None,
+            log_vport: None,
+            container_pipe_size: None,
+            server_addr: None,
+            server_retry_max_attempts: None,
+            server_retry_initial_interval: None,
+            server_retry_max_interval: None,
+            passfd_listener_port: None,
+            unified_cgroup_hierarchy: None,
+            tracing: None,
+            dump_config: None,
+            immediate_shutdown: None,
+            https_proxy: None,
+            no_proxy: None,
+            guest_components_rest_api: None,
+            guest_components_procs: None,
+            #[cfg(feature = "guest-pull")]
+            image_registry_auth: None,
+            secure_storage_integrity: None,
+            #[cfg(feature = "guest-pull")]
+            enable_signature_verification: None,
+            #[cfg(feature = "guest-pull")]
+            image_policy_file: None,
+        }
+    }
+}
+
+// A single layer fed to [`AgentConfig::resolve`]. Sources are applied in slice
+// order, so a later source overrides an earlier one on a per-field basis.
+#[derive(Clone, Debug)]
+pub enum ConfigSource {
+    /// The compiled-in defaults. Always implied as the lowest layer; listing it
+    /// explicitly simply documents the intent.
+    Defaults,
+    /// An agent configuration file, parsed according to its extension.
+    File(String),
+    /// A kernel cmdline file whose `agent.*` parameters are parsed as usual.
+    Cmdline(String),
+    /// The process environment (`KATA_AGENT_<FIELD>` variables).
+    Env,
+}
+
+impl ConfigSource {
+    // A short, stable label used in the provenance record returned by
+    // [`AgentConfig::resolve`].
+    fn label(&self) -> &'static str {
+        match self {
+            ConfigSource::Defaults => "defaults",
+            ConfigSource::File(_) => "file",
+            ConfigSource::Cmdline(_) => "cmdline",
+            ConfigSource::Env => "env",
+        }
+    }
+}
+
+// Records which [`ConfigSource`] won each field during resolution, so an
+// operator can see exactly where a surprising value came from.
+pub type ConfigProvenance = std::collections::HashMap<&'static str, &'static str>;
+
+/// Outcome of a runtime [`reload`](SharedConfig::reload): the mutable fields are
+/// applied in place, while any immutable field whose value changed is listed in
+/// `requires_restart` and left untouched.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReloadReport {
+    /// Immutable fields that differ in the new config and therefore need a full
+    /// agent restart to take effect.
+    pub requires_restart: Vec<&'static str>,
+}
+
+// Fold one source builder into the accumulating builder, recording provenance
+// for every field it actually provides.
+macro_rules! layer_field {
+    ($merged:ident, $src:ident, $prov:ident, $label:expr, $field:ident) => {
+        if let Some(v) = $src.$field {
+            $merged.$field = Some(v);
+            $prov.insert(stringify!($field), $label);
+        }
+    };
+}
+
 macro_rules! config_override {
     ($builder:ident, $config:ident, $field:ident) => {
         if let Some(v) = $builder.$field {
@@ -168,52 +565,30 @@ macro_rules! config_override {
     };
 }
 
-// parse_cmdline_param parse commandline parameters.
-macro_rules! parse_cmdline_param {
-    // commandline flags, without func to parse the option values
-    ($param:ident, $key:ident, $field:expr) => {
-        if $param.eq(&$key) {
-            $field = true;
-            continue;
-        }
-    };
-    // commandline options, with func to parse the option values
-    ($param:ident, $key:ident, $field:expr, $func:ident) => {
-        if $param.starts_with(format!("{}=", $key).as_str()) {
-            let val = $func($param)?;
-            $field = val;
-            continue;
-        }
-    };
-    // commandline options, with func to parse the option values, and match func
-    // to valid the values
-    ($param:ident, $key:ident, $field:expr, $func:ident, $guard:expr) => {
-        if $param.starts_with(format!("{}=", $key).as_str()) {
-            let val = $func($param)?;
-            if $guard(val) {
-                $field = val;
-            }
-            continue;
-        }
-    };
-}
-
 impl Default for AgentConfig {
     fn default() -> Self {
         AgentConfig {
+            config_version: DEFAULT_CONFIG_VERSION,
             debug_console: false,
             dev_mode: false,
             log_level: DEFAULT_LOG_LEVEL,
+            log_level_rules: Vec::new(),
             hotplug_timeout: DEFAULT_HOTPLUG_TIMEOUT,
             cdh_api_timeout: DEFAULT_CDH_API_TIMEOUT,
             debug_console_vport: 0,
             log_vport: 0,
             container_pipe_size: DEFAULT_CONTAINER_PIPE_SIZE,
             server_addr: format!("{}:{}", VSOCK_ADDR, DEFAULT_AGENT_VSOCK_PORT),
+            server_retry_max_attempts: DEFAULT_SERVER_RETRY_MAX_ATTEMPTS,
+            server_retry_initial_interval: DEFAULT_SERVER_RETRY_INITIAL_INTERVAL,
+            server_retry_max_interval: DEFAULT_SERVER_RETRY_MAX_INTERVAL,
             passfd_listener_port: 0,
             unified_cgroup_hierarchy: false,
             tracing: false,
             supports_seccomp: rpc::have_seccomp(),
+            dump_config: false,
+            immediate_shutdown: false,
+            config_strict: false,
             https_proxy: String::from(""),
             no_proxy: String::from(""),
             guest_components_rest_api: GuestComponentsFeatures::default(),
@@ -225,6 +600,36 @@ impl Default for AgentConfig {
             enable_signature_verification: false,
             #[cfg(feature = "guest-pull")]
             image_policy_file: String::from(""),
+            #[cfg(feature = "guest-pull")]
+            image_policy: None,
+        }
+    }
+}
+
+// Serialization formats understood by the agent configuration file loader.
+//
+// TOML remains the native format; YAML and JSON are accepted so operators can
+// ship whichever format their tooling already produces.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ConfigFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl ConfigFormat {
+    // Guess the format from a config file path's extension, falling back to
+    // TOML when the extension is unknown or absent.
+    fn from_path(path: &str) -> Self {
+        match Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("yaml") | Some("yml") => ConfigFormat::Yaml,
+            Some("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Toml,
         }
     }
 }
@@ -233,28 +638,72 @@ impl FromStr for AgentConfig {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let agent_config_builder: AgentConfigBuilder =
-            toml::from_str(s).map_err(anyhow::Error::new)?;
+        AgentConfig::from_str_with_format(s, ConfigFormat::Toml)
+    }
+}
+
+impl AgentConfig {
+    // Deserialize an `AgentConfigBuilder` from `s` using the requested format
+    // and fold it onto the compiled-in defaults.
+    fn from_str_with_format(s: &str, format: ConfigFormat) -> Result<AgentConfig> {
+        let agent_config_builder: AgentConfigBuilder = match format {
+            ConfigFormat::Toml => toml::from_str(s).map_err(anyhow::Error::new)?,
+            ConfigFormat::Yaml => serde_yaml::from_str(s).map_err(anyhow::Error::new)?,
+            ConfigFormat::Json => serde_json::from_str(s).map_err(anyhow::Error::new)?,
+        };
         let mut agent_config: AgentConfig = Default::default();
+        agent_config.override_from_builder(agent_config_builder)?;
+        agent_config.apply_version_gates();
+        agent_config.validate()?;
+        Ok(agent_config)
+    }
+
+    // Fold the `Some` fields of a builder onto an existing config, converting
+    // string-encoded fields (e.g. the log level) through their usual parsers.
+    fn override_from_builder(&mut self, builder: AgentConfigBuilder) -> Result<()> {
+        let agent_config_builder = builder;
+        let agent_config = self;
 
         // Overwrite default values with the configuration files ones.
+        config_override!(agent_config_builder, agent_config, config_version);
         config_override!(agent_config_builder, agent_config, debug_console);
         config_override!(agent_config_builder, agent_config, dev_mode);
-        config_override!(
-            agent_config_builder,
-            agent_config,
-            log_level,
-            logrus_to_slog_level
-        );
+        // `log_level` carries an `agent.log`-style directive list. Parse the
+        // bare default level out of it (invalid tokens are dropped, leaving the
+        // existing default in place, as the tests expect).
+        if let Some(spec) = agent_config_builder.log_level {
+            let (default, rules) = parse_log_directives(&spec);
+            if let Some(level) = default {
+                agent_config.log_level = level;
+            }
+            // Prefer rules the source pre-parsed (cmdline/env); otherwise fall
+            // back to any embedded in the spec string itself (config file).
+            match agent_config_builder.log_level_rules {
+                Some(rules) => agent_config.log_level_rules = rules,
+                None if !rules.is_empty() => agent_config.log_level_rules = rules,
+                None => {}
+            }
+        } else if let Some(rules) = agent_config_builder.log_level_rules {
+            agent_config.log_level_rules = rules;
+        }
         config_override!(agent_config_builder, agent_config, hotplug_timeout);
         config_override!(agent_config_builder, agent_config, cdh_api_timeout);
         config_override!(agent_config_builder, agent_config, debug_console_vport);
         config_override!(agent_config_builder, agent_config, log_vport);
         config_override!(agent_config_builder, agent_config, container_pipe_size);
         config_override!(agent_config_builder, agent_config, server_addr);
+        config_override!(agent_config_builder, agent_config, server_retry_max_attempts);
+        config_override!(
+            agent_config_builder,
+            agent_config,
+            server_retry_initial_interval
+        );
+        config_override!(agent_config_builder, agent_config, server_retry_max_interval);
         config_override!(agent_config_builder, agent_config, passfd_listener_port);
         config_override!(agent_config_builder, agent_config, unified_cgroup_hierarchy);
         config_override!(agent_config_builder, agent_config, tracing);
+        config_override!(agent_config_builder, agent_config, dump_config);
+        config_override!(agent_config_builder, agent_config, immediate_shutdown);
         config_override!(agent_config_builder, agent_config, https_proxy);
         config_override!(agent_config_builder, agent_config, no_proxy);
         config_override!(
@@ -275,159 +724,131 @@ impl FromStr for AgentConfig {
         }
         config_override!(agent_config_builder, agent_config, secure_storage_integrity);
 
-        Ok(agent_config)
+        Ok(())
     }
 }
 
 impl AgentConfig {
     #[instrument]
-    #[allow(clippy::redundant_closure_call)]
     pub fn from_cmdline(file: &str, args: Vec<String>) -> Result<AgentConfig> {
+        // The --dump-config CLI arg forces introspection mode regardless of how
+        // the rest of the configuration is sourced.
+        let dump_config_from_args = args.iter().any(|a| a == DUMP_CONFIG_ARG);
+
+        // Strict validation can also be requested via --config-check, and its
+        // diagnostics rendered as a JSON array via `--format json`.
+        let config_check_from_args = args.iter().any(|a| a == CONFIG_CHECK_ARG);
+        let json_diagnostics = args
+            .iter()
+            .position(|a| a == FORMAT_ARG)
+            .and_then(|p| args.get(p + 1))
+            .map(|f| f == "json")
+            .unwrap_or(false);
+
         // If config file specified in the args, generate our config from it
         let config_position = args.iter().position(|a| a == "--config" || a == "-c");
         if let Some(config_position) = config_position {
             if let Some(config_file) = args.get(config_position + 1) {
                 let mut config =
                     AgentConfig::from_config_file(config_file).context("AgentConfig from args")?;
-                config.override_config_from_envs();
+                config.override_config_from_envs()?;
+                // An env-sourced config_version can narrow the negotiated
+                // feature set, so re-gate version-dependent fields afterwards.
+                config.apply_version_gates();
+                config.dump_config |= dump_config_from_args;
+                config.config_strict |= config_check_from_args;
+                config.validate()?;
                 return Ok(config);
             } else {
                 panic!("The config argument wasn't formed properly: {:?}", args);
             }
         }
 
-        let mut config: AgentConfig = Default::default();
         let cmdline = fs::read_to_string(file)?;
-        let params: Vec<&str> = cmdline.split_ascii_whitespace().collect();
-        for param in params.iter() {
-            // If we get a configuration file path from the command line, we
-            // generate our config from it.
-            // The agent will fail to start if the configuration file is not present,
-            // or if it can't be parsed properly.
+
+        // A configuration file named on the kernel cmdline supersedes every
+        // other `agent.*` parameter. It is still subject to the process
+        // environment and to the introspection CLI flags, which apply
+        // regardless of how the configuration is sourced; the agent fails to
+        // start if the file is absent or cannot be parsed.
+        for param in cmdline.split_ascii_whitespace() {
             if param.starts_with(format!("{}=", CONFIG_FILE).as_str()) {
                 let config_file = get_string_value(param)?;
-                return AgentConfig::from_config_file(&config_file)
-                    .context("AgentConfig from kernel cmdline");
+                let mut config = AgentConfig::from_config_file(&config_file)
+                    .context("AgentConfig from kernel cmdline")?;
+                config.override_config_from_envs()?;
+                config.apply_version_gates();
+                config.dump_config |= dump_config_from_args;
+                config.config_strict |= config_check_from_args;
+                config.validate()?;
+                return Ok(config);
             }
+        }
 
-            // parse cmdline flags
-            parse_cmdline_param!(param, DEBUG_CONSOLE_FLAG, config.debug_console);
-            parse_cmdline_param!(param, DEV_MODE_FLAG, config.dev_mode);
-
-            // Support "bare" tracing option for backwards compatibility with
-            // Kata 1.x.
-            if param == &TRACE_MODE_OPTION {
-                config.tracing = true;
-                continue;
+        // Parse every flag and value option through the single registry
+        // (`builder_from_cmdline` / `cmdline_options`), then fold the result
+        // onto the compiled-in defaults. This is the same table the layered
+        // `resolve` and the collecting/strict error paths drive off, so there
+        // is exactly one definition per option.
+        let builder = AgentConfig::builder_from_cmdline(&cmdline)?;
+        let mut config: AgentConfig = Default::default();
+        config.override_from_builder(builder)?;
+
+        // A few cmdline tokens are not builder fields: `config_strict` is a
+        // validation mode rather than a persisted value, and (under guest-pull)
+        // the inline base64 image policy is applied straight onto the config.
+        for param in cmdline.split_ascii_whitespace() {
+            if param == CONFIG_STRICT_OPTION {
+                config.config_strict = true;
+            } else if param.starts_with(format!("{}=", CONFIG_STRICT_OPTION).as_str()) {
+                config.config_strict = get_bool_value(param)?;
             }
+            #[cfg(feature = "guest-pull")]
+            if param.starts_with(format!("{}=", IMAGE_POLICY_OPTION).as_str()) {
+                config.image_policy = get_image_policy_value(param)?;
+            }
+        }
 
-            parse_cmdline_param!(param, TRACE_MODE_OPTION, config.tracing, get_bool_value);
-
-            // parse cmdline options
-            parse_cmdline_param!(param, LOG_LEVEL_OPTION, config.log_level, get_log_level);
-            parse_cmdline_param!(
-                param,
-                SERVER_ADDR_OPTION,
-                config.server_addr,
-                get_string_value
-            );
-
-            // ensure the timeout is a positive value
-            parse_cmdline_param!(
-                param,
-                HOTPLUG_TIMOUT_OPTION,
-                config.hotplug_timeout,
-                get_timeout,
-                |hotplug_timeout: time::Duration| hotplug_timeout.as_secs() > 0
-            );
+        config.override_config_from_envs()?;
+        config.apply_version_gates();
+        config.dump_config |= dump_config_from_args;
+        config.config_strict |= config_check_from_args;
 
-            // ensure the timeout is a positive value
-            parse_cmdline_param!(
-                param,
-                CDH_API_TIMOUT_OPTION,
-                config.cdh_api_timeout,
-                get_timeout,
-                |cdh_api_timeout: time::Duration| cdh_api_timeout.as_secs() > 0
-            );
+        // An inline, base64-encoded policy takes precedence over a policy file.
+        #[cfg(feature = "guest-pull")]
+        if config.image_policy.is_some() {
+            config.image_policy_file.clear();
+        }
 
-            // vsock port should be positive values
-            parse_cmdline_param!(
-                param,
-                DEBUG_CONSOLE_VPORT_OPTION,
-                config.debug_console_vport,
-                get_vsock_port,
-                |port| port > 0
-            );
-            parse_cmdline_param!(
-                param,
-                LOG_VPORT_OPTION,
-                config.log_vport,
-                get_vsock_port,
-                |port| port > 0
-            );
-            parse_cmdline_param!(
-                param,
-                PASSFD_LISTENER_PORT,
-                config.passfd_listener_port,
-                get_vsock_port,
-                |port| port > 0
-            );
-            parse_cmdline_param!(
-                param,
-                CONTAINER_PIPE_SIZE_OPTION,
-                config.container_pipe_size,
-                get_container_pipe_size
-            );
-            parse_cmdline_param!(
-                param,
-                UNIFIED_CGROUP_HIERARCHY_OPTION,
-                config.unified_cgroup_hierarchy,
-                get_bool_value
-            );
-            parse_cmdline_param!(param, HTTPS_PROXY, config.https_proxy, get_url_value);
-            parse_cmdline_param!(param, NO_PROXY, config.no_proxy, get_string_value);
-            parse_cmdline_param!(
-                param,
-                GUEST_COMPONENTS_REST_API_OPTION,
-                config.guest_components_rest_api,
-                get_guest_components_features_value
-            );
-            parse_cmdline_param!(
-                param,
-                GUEST_COMPONENTS_PROCS_OPTION,
-                config.guest_components_procs,
-                get_guest_components_procs_value
-            );
-            #[cfg(feature = "guest-pull")]
-            {
-                parse_cmdline_param!(
-                    param,
-                    IMAGE_REGISTRY_AUTH_OPTION,
-                    config.image_registry_auth,
-                    get_string_value
-                );
-                parse_cmdline_param!(
-                    param,
-                    ENABLE_SIGNATURE_VERIFICATION,
-                    config.enable_signature_verification,
-                    get_bool_value
-                );
-                parse_cmdline_param!(
-                    param,
-                    IMAGE_POLICY_FILE,
-                    config.image_policy_file,
-                    get_string_value
+        // In strict mode, collect every dubious key/value across sources and
+        // fail loudly instead of silently falling back to defaults.
+        if config.config_strict {
+            let diagnostics = collect_diagnostics(&cmdline);
+            if !diagnostics.is_empty() {
+                // `--format json` is a machine-readable mode: emit the bare
+                // `serde_json` array on stdout so tooling can parse it without
+                // stripping any prose, then return an error carrying only a
+                // short plain-text reason.
+                if json_diagnostics {
+                    let rendered = serde_json::to_string_pretty(&diagnostics)
+                        .with_context(|| "failed to render JSON diagnostics")?;
+                    println!("{}", rendered);
+                    bail!("{} invalid agent configuration parameter(s)", diagnostics.len());
+                }
+                let rendered = diagnostics
+                    .iter()
+                    .map(|d| format!("  {} = {:?} ({}): {}", d.key, d.value, serde_json::to_string(&d.source).unwrap_or_default(), d.reason))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                bail!(
+                    "{} invalid agent configuration parameter(s):\n{}",
+                    diagnostics.len(),
+                    rendered
                 );
             }
-            parse_cmdline_param!(
-                param,
-                SECURE_STORAGE_INTEGRITY_OPTION,
-                config.secure_storage_integrity,
-                get_bool_value
-            );
         }
 
-        config.override_config_from_envs();
+        config.validate()?;
 
         Ok(config)
     }
@@ -436,26 +857,754 @@ impl AgentConfig {
     pub fn from_config_file(file: &str) -> Result<AgentConfig> {
         let config = fs::read_to_string(file)
             .with_context(|| format!("Failed to read config file {}", file))?;
-        AgentConfig::from_str(&config)
+        AgentConfig::from_str_with_format(&config, ConfigFormat::from_path(file))
     }
 
+    // Fold every `KATA_AGENT_<FIELD>` environment variable onto an already
+    // sourced config. This drives off the same [`builder_from_envs`] table as
+    // the layered `resolve` path, so a variable accepted by strict validation
+    // (see `ENV_DIAGNOSTIC_KEYS`) actually takes effect at boot rather than
+    // being silently ignored — every advertised field is covered, not just the
+    // handful the server loop reads directly.
     #[instrument]
-    fn override_config_from_envs(&mut self) {
-        if let Ok(addr) = env::var(SERVER_ADDR_ENV_VAR) {
-            self.server_addr = addr;
+    fn override_config_from_envs(&mut self) -> Result<()> {
+        self.override_from_builder(Self::builder_from_envs())
+    }
+
+    // Parse the kernel cmdline like [`from_cmdline`], but instead of aborting at
+    // the first malformed token, probe every registered parser and accumulate
+    // all failures into one error. The returned error's top-level message counts
+    // the offending parameters and its `.chain()` lists each `key=value` token
+    // together with its individual cause, so an operator with several mistakes
+    // sees all of them at once. On a clean cmdline this defers to `from_cmdline`.
+    #[instrument]
+    pub fn from_cmdline_collect(file: &str, args: Vec<String>) -> Result<AgentConfig> {
+        // A config file (via `--config`/`-c` or `agent.config_file=`) makes
+        // `from_cmdline` ignore the remaining cmdline tokens, so collecting their
+        // parse errors here would reject an otherwise-valid boot. Defer to the
+        // normal path in that case.
+        let has_config_arg = args.iter().any(|a| a == "--config" || a == "-c");
+        let cmdline = fs::read_to_string(file)?;
+        let has_config_file = cmdline
+            .split_ascii_whitespace()
+            .any(|p| p.starts_with(&format!("{}=", CONFIG_FILE)));
+
+        if !has_config_arg && !has_config_file {
+            let errors = collect_parse_errors(&cmdline);
+            if !errors.is_empty() {
+                return Err(combine_parse_errors(errors));
+            }
         }
 
-        if let Ok(addr) = env::var(LOG_LEVEL_ENV_VAR) {
-            if let Ok(level) = logrus_to_slog_level(&addr) {
-                self.log_level = level;
+        AgentConfig::from_cmdline(file, args)
+    }
+
+    /// Whether the negotiated [`config_version`](AgentConfig::config_version)
+    /// is new enough to understand the `cdh_api_timeout` option.
+    pub fn supports_cdh_api_timeout(&self) -> bool {
+        self.config_version >= CONFIG_VERSION_CDH_API_TIMEOUT
+    }
+
+    /// Whether the negotiated [`config_version`](AgentConfig::config_version)
+    /// is new enough to understand the guest-components selectors
+    /// (`guest_components_rest_api` / `guest_components_procs`).
+    pub fn supports_guest_components_rest_api(&self) -> bool {
+        self.config_version >= CONFIG_VERSION_GUEST_COMPONENTS
+    }
+
+    // Drop options the negotiated version predates, so an agent speaking an
+    // older `config_version` degrades to the defaults for anything introduced
+    // later instead of honouring values a peer of that version would never set.
+    fn apply_version_gates(&mut self) {
+        if !self.supports_cdh_api_timeout() {
+            self.cdh_api_timeout = DEFAULT_CDH_API_TIMEOUT;
+        }
+        if !self.supports_guest_components_rest_api() {
+            self.guest_components_rest_api = GuestComponentsFeatures::default();
+            self.guest_components_procs = GuestComponentsProcs::default();
+        }
+    }
+
+    // Apply the runtime-mutable fields of `new` onto `self` in place, returning
+    // a report of any immutable fields that changed (and were therefore left
+    // alone, requiring a restart to take effect). Only the log verbosity and the
+    // various `*_timeout` values are safe to change live; everything else —
+    // listeners, ports, feature selectors — is fixed at boot.
+    pub fn apply_reload(&mut self, new: &AgentConfig) -> ReloadReport {
+        let mut report = ReloadReport::default();
+
+        // Mutable: log verbosity and timeouts are overwritten in place. This
+        // only updates the config; no logger drain is reconfigured here.
+        self.log_level = new.log_level;
+        self.log_level_rules = new.log_level_rules.clone();
+        self.hotplug_timeout = new.hotplug_timeout;
+        self.cdh_api_timeout = new.cdh_api_timeout;
+
+        // Immutable: diff and report, but do not apply.
+        macro_rules! immutable {
+            ($field:ident) => {
+                if self.$field != new.$field {
+                    report.requires_restart.push(stringify!($field));
+                }
+            };
+        }
+        immutable!(config_version);
+        immutable!(debug_console);
+        immutable!(dev_mode);
+        immutable!(server_addr);
+        immutable!(server_retry_max_attempts);
+        immutable!(server_retry_initial_interval);
+        immutable!(server_retry_max_interval);
+        immutable!(debug_console_vport);
+        immutable!(log_vport);
+        immutable!(passfd_listener_port);
+        immutable!(container_pipe_size);
+        immutable!(unified_cgroup_hierarchy);
+        immutable!(tracing);
+        immutable!(https_proxy);
+        immutable!(no_proxy);
+        immutable!(guest_components_rest_api);
+        immutable!(guest_components_procs);
+        immutable!(secure_storage_integrity);
+        #[cfg(feature = "guest-pull")]
+        {
+            immutable!(image_registry_auth);
+            immutable!(enable_signature_verification);
+            immutable!(image_policy_file);
+        }
+
+        report
+    }
+
+    // Resolve the effective log level for a record emitted from `target`
+    // (a module path or tag). The first rule whose target is a prefix of
+    // `target` wins; otherwise the global default applies. This is a lookup
+    // only; it does not install a filter on any logger drain.
+    pub fn log_level_for(&self, target: &str) -> slog::Level {
+        self.log_level_rules
+            .iter()
+            .find(|(prefix, _)| target.starts_with(prefix.as_str()))
+            .map(|(_, level)| *level)
+            .unwrap_or(self.log_level)
+    }
+
+    // Produce the reconnect backoff schedule for the agent→runtime server
+    // connection. The interval starts at `server_retry_initial_interval` and
+    // doubles after each failed attempt, capped at `server_retry_max_interval`.
+    // The iterator yields at most `server_retry_max_attempts` intervals; a value
+    // of `0` means retry forever. This only computes the schedule; it makes
+    // no connection attempts and does not sleep.
+    pub fn server_retry_backoff(&self) -> impl Iterator<Item = time::Duration> {
+        let max_attempts = self.server_retry_max_attempts;
+        let max_interval = self.server_retry_max_interval;
+        let mut interval = self.server_retry_initial_interval;
+        let mut attempt: u32 = 0;
+
+        std::iter::from_fn(move || {
+            if max_attempts != 0 && attempt >= max_attempts {
+                return None;
             }
+            attempt += 1;
+
+            let current = interval.min(max_interval);
+            // Double towards the ceiling, saturating rather than overflowing.
+            interval = interval.saturating_mul(2).min(max_interval);
+            Some(current)
+        })
+    }
+
+    // Serialize the fully resolved configuration as TOML, for the
+    // `--dump-config` / `agent.dump_config` introspection mode. This only
+    // returns the text; it prints nothing and does not stop the agent.
+    pub fn to_toml(&self) -> Result<String> {
+        let mut doc = String::new();
+        doc.push_str(&format!("config_version = {}\n", self.config_version));
+        doc.push_str(&format!("debug_console = {}\n", self.debug_console));
+        doc.push_str(&format!("dev_mode = {}\n", self.dev_mode));
+        // Fold the default level and any per-target rules back into a single
+        // `agent.log`-style directive string, so the dump round-trips through
+        // the parser and an operator can see the resolved rules.
+        let mut log_directive = slog_level_to_logrus(self.log_level).to_string();
+        for (target, level) in &self.log_level_rules {
+            log_directive.push_str(&format!(",{}={}", target, slog_level_to_logrus(*level)));
         }
+        doc.push_str(&format!("log_level = \"{}\"\n", log_directive));
+        doc.push_str(&format!(
+            "hotplug_timeout = {}\n",
+            self.hotplug_timeout.as_secs()
+        ));
+        doc.push_str(&format!(
+            "cdh_api_timeout = {}\n",
+            self.cdh_api_timeout.as_secs()
+        ));
+        doc.push_str(&format!(
+            "debug_console_vport = {}\n",
+            self.debug_console_vport
+        ));
+        doc.push_str(&format!("log_vport = {}\n", self.log_vport));
+        doc.push_str(&format!(
+            "container_pipe_size = {}\n",
+            self.container_pipe_size
+        ));
+        doc.push_str(&format!("server_addr = \"{}\"\n", self.server_addr));
+        doc.push_str(&format!(
+            "server_retry_max_attempts = {}\n",
+            self.server_retry_max_attempts
+        ));
+        doc.push_str(&format!(
+            "server_retry_initial_interval = {}\n",
+            self.server_retry_initial_interval.as_secs()
+        ));
+        doc.push_str(&format!(
+            "server_retry_max_interval = {}\n",
+            self.server_retry_max_interval.as_secs()
+        ));
+        doc.push_str(&format!(
+            "passfd_listener_port = {}\n",
+            self.passfd_listener_port
+        ));
+        doc.push_str(&format!(
+            "unified_cgroup_hierarchy = {}\n",
+            self.unified_cgroup_hierarchy
+        ));
+        doc.push_str(&format!("tracing = {}\n", self.tracing));
+        doc.push_str(&format!("supports_seccomp = {}\n", self.supports_seccomp));
+        doc.push_str(&format!("dump_config = {}\n", self.dump_config));
+        doc.push_str(&format!("immediate_shutdown = {}\n", self.immediate_shutdown));
+        doc.push_str(&format!("https_proxy = \"{}\"\n", self.https_proxy));
+        doc.push_str(&format!("no_proxy = \"{}\"\n", self.no_proxy));
+        doc.push_str(&format!(
+            "guest_components_rest_api = \"{}\"\n",
+            self.guest_components_rest_api
+        ));
+        doc.push_str(&format!(
+            "guest_components_procs = \"{}\"\n",
+            self.guest_components_procs
+        ));
+        doc.push_str(&format!(
+            "secure_storage_integrity = {}\n",
+            self.secure_storage_integrity
+        ));
+        #[cfg(feature = "guest-pull")]
+        {
+            doc.push_str(&format!(
+                "image_registry_auth = \"{}\"\n",
+                self.image_registry_auth
+            ));
+            doc.push_str(&format!(
+                "enable_signature_verification = {}\n",
+                self.enable_signature_verification
+            ));
+            doc.push_str(&format!(
+                "image_policy_file = \"{}\"\n",
+                self.image_policy_file
+            ));
+            // The inline image policy is decoded from a base64 cmdline token and
+            // has no config-file key, so summarise it as a comment for the dump.
+            if let Some(policy) = &self.image_policy {
+                doc.push_str(&format!(
+                    "# image_policy: {} condition(s), expires {}\n",
+                    policy.conditions.len(),
+                    policy.expiration
+                ));
+            }
+        }
+        Ok(doc)
+    }
+
+    // Collect every `KATA_AGENT_<FIELD>` environment variable into a builder,
+    // parsed through the same helpers the kernel cmdline uses. Variables that
+    // are absent or fail to parse leave their field unset (preserving the
+    // historical "invalid value silently ignored" behaviour).
+    fn builder_from_envs() -> AgentConfigBuilder {
+        let mut b = AgentConfigBuilder::empty();
 
+        // Reuse the cmdline parsers by rebuilding a "<cmdline-key>=<value>"
+        // string from each environment variable.
+        let param = |var: &str, key: &str| env::var(var).ok().map(|v| format!("{}={}", key, v));
+
+        if let Some(p) = param(CONFIG_VERSION_ENV_VAR, CONFIG_VERSION_OPTION) {
+            b.config_version = get_config_version(&p).ok();
+        }
+        if let Some(p) = param("KATA_AGENT_DEBUG_CONSOLE", DEBUG_CONSOLE_FLAG) {
+            b.debug_console = get_bool_value(&p).ok();
+        }
+        if let Some(p) = param("KATA_AGENT_DEV_MODE", DEV_MODE_FLAG) {
+            b.dev_mode = get_bool_value(&p).ok();
+        }
+        if let Ok(spec) = env::var(LOG_LEVEL_ENV_VAR) {
+            // Carry the bare default level and the per-target rules separately.
+            // An invalid or rules-only value leaves the default unset, so weaker
+            // layers keep theirs — matching the historical "silent fallback to
+            // default" behaviour.
+            let (default, rules) = parse_log_directives(&spec);
+            if let Some(level) = default {
+                b.log_level = Some(slog_level_to_logrus(level).to_string());
+            }
+            if !rules.is_empty() {
+                b.log_level_rules = Some(rules);
+            }
+        }
+        if let Some(p) = param("KATA_AGENT_HOTPLUG_TIMEOUT", HOTPLUG_TIMOUT_OPTION) {
+            b.hotplug_timeout = get_timeout(&p).ok().filter(|d| d.as_secs() > 0);
+        }
+        if let Some(p) = param("KATA_AGENT_CDH_API_TIMEOUT", CDH_API_TIMOUT_OPTION) {
+            b.cdh_api_timeout = get_timeout(&p).ok().filter(|d| d.as_secs() > 0);
+        }
+        if let Some(p) = param("KATA_AGENT_DEBUG_CONSOLE_VPORT", DEBUG_CONSOLE_VPORT_OPTION) {
+            b.debug_console_vport = get_vsock_port(&p).ok().filter(|port| *port > 0);
+        }
+        if let Some(p) = param("KATA_AGENT_LOG_VPORT", LOG_VPORT_OPTION) {
+            b.log_vport = get_vsock_port(&p).ok().filter(|port| *port > 0);
+        }
+        if let Some(p) = param("KATA_AGENT_CONTAINER_PIPE_SIZE", CONTAINER_PIPE_SIZE_OPTION) {
+            b.container_pipe_size = get_container_pipe_size(&p).ok();
+        }
+        if let Ok(addr) = env::var(SERVER_ADDR_ENV_VAR) {
+            b.server_addr = Some(addr);
+        }
+        if let Some(p) = param(
+            SERVER_RETRY_MAX_ATTEMPTS_ENV_VAR,
+            SERVER_RETRY_MAX_ATTEMPTS_OPTION,
+        ) {
+            b.server_retry_max_attempts = get_retry_max_attempts(&p).ok();
+        }
+        if let Some(p) = param(
+            SERVER_RETRY_INITIAL_INTERVAL_ENV_VAR,
+            SERVER_RETRY_INITIAL_INTERVAL_OPTION,
+        ) {
+            b.server_retry_initial_interval = get_retry_interval(&p).ok().filter(|d| d.as_secs() > 0);
+        }
+        if let Some(p) = param(
+            SERVER_RETRY_MAX_INTERVAL_ENV_VAR,
+            SERVER_RETRY_MAX_INTERVAL_OPTION,
+        ) {
+            b.server_retry_max_interval = get_retry_interval(&p).ok().filter(|d| d.as_secs() > 0);
+        }
+        if let Some(p) = param("KATA_AGENT_PASSFD_LISTENER_PORT", PASSFD_LISTENER_PORT) {
+            b.passfd_listener_port = get_vsock_port(&p).ok().filter(|port| *port > 0);
+        }
+        if let Some(p) = param(
+            "KATA_AGENT_UNIFIED_CGROUP_HIERARCHY",
+            UNIFIED_CGROUP_HIERARCHY_OPTION,
+        ) {
+            b.unified_cgroup_hierarchy = get_bool_value(&p).ok();
+        }
         if let Ok(value) = env::var(TRACING_ENV_VAR) {
             let name_value = format!("{}={}", TRACING_ENV_VAR, value);
+            b.tracing = Some(get_bool_value(&name_value).unwrap_or(false));
+        }
+        if let Some(p) = param(DUMP_CONFIG_ENV_VAR, DUMP_CONFIG_OPTION) {
+            b.dump_config = get_bool_value(&p).ok();
+        }
+        if let Some(p) = param(IMMEDIATE_SHUTDOWN_ENV_VAR, IMMEDIATE_SHUTDOWN_OPTION) {
+            b.immediate_shutdown = get_bool_value(&p).ok();
+        }
+        if let Some(p) = param("KATA_AGENT_HTTPS_PROXY", HTTPS_PROXY) {
+            b.https_proxy = get_url_value(&p).ok();
+        }
+        if let Some(p) = param("KATA_AGENT_NO_PROXY", NO_PROXY) {
+            b.no_proxy = get_string_value(&p).ok();
+        }
+        if let Some(p) = param(
+            "KATA_AGENT_GUEST_COMPONENTS_REST_API",
+            GUEST_COMPONENTS_REST_API_OPTION,
+        ) {
+            b.guest_components_rest_api = get_guest_components_features_value(&p).ok();
+        }
+        if let Some(p) = param(
+            "KATA_AGENT_GUEST_COMPONENTS_PROCS",
+            GUEST_COMPONENTS_PROCS_OPTION,
+        ) {
+            b.guest_components_procs = get_guest_components_procs_value(&p).ok();
+        }
+        if let Some(p) = param(
+            "KATA_AGENT_SECURE_STORAGE_INTEGRITY",
+            SECURE_STORAGE_INTEGRITY_OPTION,
+        ) {
+            b.secure_storage_integrity = get_bool_value(&p).ok();
+        }
+        #[cfg(feature = "guest-pull")]
+        {
+            if let Some(p) = param("KATA_AGENT_IMAGE_REGISTRY_AUTH", IMAGE_REGISTRY_AUTH_OPTION) {
+                b.image_registry_auth = get_string_value(&p).ok();
+            }
+            if let Some(p) = param(
+                "KATA_AGENT_ENABLE_SIGNATURE_VERIFICATION",
+                ENABLE_SIGNATURE_VERIFICATION,
+            ) {
+                b.enable_signature_verification = get_bool_value(&p).ok();
+            }
+            if let Some(p) = param("KATA_AGENT_IMAGE_POLICY_FILE", IMAGE_POLICY_FILE) {
+                b.image_policy_file = get_string_value(&p).ok();
+            }
+        }
+
+        b
+    }
+
+    // Resolve the final configuration by layering `sources` over the compiled
+    // defaults with a documented precedence (earlier in the slice is weaker).
+    //
+    // Every field is overridable by every source; the returned
+    // [`ConfigProvenance`] records, per field, which source won, so a
+    // surprising value can be traced back to its origin.
+    pub fn resolve(sources: &[ConfigSource]) -> Result<(AgentConfig, ConfigProvenance)> {
+        let mut merged = AgentConfigBuilder::empty();
+        let mut prov = ConfigProvenance::new();
+
+        for source in sources {
+            let label = source.label();
+            let builder = match source {
+                // Defaults are the base config onto which everything is folded.
+                ConfigSource::Defaults => continue,
+                ConfigSource::File(path) => {
+                    let contents = fs::read_to_string(path)
+                        .with_context(|| format!("Failed to read config file {}", path))?;
+                    let format = ConfigFormat::from_path(path);
+                    match format {
+                        ConfigFormat::Toml => toml::from_str(&contents).map_err(anyhow::Error::new)?,
+                        ConfigFormat::Yaml => {
+                            serde_yaml::from_str(&contents).map_err(anyhow::Error::new)?
+                        }
+                        ConfigFormat::Json => {
+                            serde_json::from_str(&contents).map_err(anyhow::Error::new)?
+                        }
+                    }
+                }
+                ConfigSource::Cmdline(path) => {
+                    let cmdline = fs::read_to_string(path)?;
+                    AgentConfig::builder_from_cmdline(&cmdline)?
+                }
+                ConfigSource::Env => AgentConfig::builder_from_envs(),
+            };
+
+            layer_field!(merged, builder, prov, label, config_version);
+            layer_field!(merged, builder, prov, label, debug_console);
+            layer_field!(merged, builder, prov, label, dev_mode);
+            layer_field!(merged, builder, prov, label, log_level);
+            layer_field!(merged, builder, prov, label, log_level_rules);
+            layer_field!(merged, builder, prov, label, hotplug_timeout);
+            layer_field!(merged, builder, prov, label, cdh_api_timeout);
+            layer_field!(merged, builder, prov, label, debug_console_vport);
+            layer_field!(merged, builder, prov, label, log_vport);
+            layer_field!(merged, builder, prov, label, container_pipe_size);
+            layer_field!(merged, builder, prov, label, server_addr);
+            layer_field!(merged, builder, prov, label, server_retry_max_attempts);
+            layer_field!(merged, builder, prov, label, server_retry_initial_interval);
+            layer_field!(merged, builder, prov, label, server_retry_max_interval);
+            layer_field!(merged, builder, prov, label, passfd_listener_port);
+            layer_field!(merged, builder, prov, label, unified_cgroup_hierarchy);
+            layer_field!(merged, builder, prov, label, tracing);
+            layer_field!(merged, builder, prov, label, dump_config);
+            layer_field!(merged, builder, prov, label, immediate_shutdown);
+            layer_field!(merged, builder, prov, label, https_proxy);
+            layer_field!(merged, builder, prov, label, no_proxy);
+            layer_field!(merged, builder, prov, label, guest_components_rest_api);
+            layer_field!(merged, builder, prov, label, guest_components_procs);
+            layer_field!(merged, builder, prov, label, secure_storage_integrity);
+            #[cfg(feature = "guest-pull")]
+            {
+                layer_field!(merged, builder, prov, label, image_registry_auth);
+                layer_field!(merged, builder, prov, label, enable_signature_verification);
+                layer_field!(merged, builder, prov, label, image_policy_file);
+            }
+        }
+
+        let mut config: AgentConfig = Default::default();
+        config.override_from_builder(merged)?;
+        config.apply_version_gates();
+
+        Ok((config, prov))
+    }
+
+    // Cross-check the resolved configuration for combinations that parsed
+    // cleanly field-by-field but can never be honoured together. Returns an
+    // actionable error describing the first inconsistency found.
+    pub fn validate(&self) -> Result<()> {
+        // Distinct vsock vports must not share a number.
+        if self.debug_console_vport > 0
+            && self.log_vport > 0
+            && self.debug_console_vport == self.log_vport
+        {
+            bail!(
+                "{}: debug_console_vport and log_vport are both {}",
+                ERR_VSOCK_PORT_COLLISION,
+                self.log_vport
+            );
+        }
+
+        // The server address must parse; for a vsock address its port must not
+        // collide with any vsock listener the agent also opens.
+        let server_port = parse_server_addr_port(&self.server_addr)
+            .with_context(|| format!("{}: {:?}", ERR_SERVER_ADDR_PARSE, self.server_addr))?;
+
+        if let Some(port) = server_port {
+            for (name, vport) in [
+                ("passfd_listener_port", self.passfd_listener_port),
+                ("debug_console_vport", self.debug_console_vport),
+                ("log_vport", self.log_vport),
+            ] {
+                if vport > 0 && vport == port {
+                    bail!(
+                        "{}: {} ({}) collides with the server_addr vsock port",
+                        ERR_VSOCK_PORT_COLLISION,
+                        name,
+                        vport
+                    );
+                }
+            }
+        }
+
+        // The reconnect backoff must be able to grow: an initial interval above
+        // the ceiling would be silently capped, discarding the operator's value.
+        if self.server_retry_initial_interval > self.server_retry_max_interval {
+            bail!(
+                "server_retry_initial_interval ({}s) must not exceed server_retry_max_interval ({}s)",
+                self.server_retry_initial_interval.as_secs(),
+                self.server_retry_max_interval.as_secs()
+            );
+        }
+
+        // The attestation/all REST surfaces are only reachable when the
+        // api-server-rest process is spawned; requesting them otherwise can
+        // never be satisfied. (`resource` is the benign default and needs no
+        // REST server.)
+        if matches!(
+            self.guest_components_rest_api,
+            GuestComponentsFeatures::Attestation | GuestComponentsFeatures::All
+        ) && self.guest_components_procs != GuestComponentsProcs::ApiServerRest
+        {
+            bail!(
+                "{}: guest_components_rest_api={} but guest_components_procs={}",
+                ERR_REST_API_WITHOUT_SERVER,
+                self.guest_components_rest_api,
+                self.guest_components_procs
+            );
+        }
+
+        Ok(())
+    }
+
+    // Parse a kernel cmdline string into a builder, so it can take part in the
+    // layered [`resolve`] pipeline without clobbering weaker sources' fields.
+    fn builder_from_cmdline(cmdline: &str) -> Result<AgentConfigBuilder> {
+        let mut b = AgentConfigBuilder::empty();
+        let options = cmdline_options();
+
+        'tokens: for param in cmdline.split_ascii_whitespace() {
+            // Bare flags carry no value and cannot fail to parse, so they are
+            // matched directly rather than through the value registry.
+            if param == DEBUG_CONSOLE_FLAG {
+                b.debug_console = Some(true);
+                continue;
+            } else if param == DEV_MODE_FLAG {
+                b.dev_mode = Some(true);
+                continue;
+            } else if param == TRACE_MODE_OPTION {
+                b.tracing = Some(true);
+                continue;
+            } else if param == DUMP_CONFIG_OPTION {
+                b.dump_config = Some(true);
+                continue;
+            } else if param == IMMEDIATE_SHUTDOWN_OPTION {
+                b.immediate_shutdown = Some(true);
+                continue;
+            }
+
+            // Everything else is a `key=value` option resolved through the
+            // single registry.
+            for opt in &options {
+                if param.starts_with(format!("{}=", opt.key).as_str()) {
+                    (opt.apply)(param, &mut b)?;
+                    continue 'tokens;
+                }
+            }
+        }
+
+        Ok(b)
+    }
+}
+
+/// A hot-reloadable handle around a shared [`AgentConfig`].
+///
+/// The config sits behind an `Arc<RwLock<_>>` so one instance can be shared.
+/// [`reload`](SharedConfig::reload) re-parses the backing config file and
+/// applies the runtime-mutable fields under a write lock — immutable fields
+/// are diffed and reported as requiring a restart. Nothing here calls
+/// `reload` on its own; the caller decides when to run it.
+#[derive(Clone)]
+pub struct SharedConfig {
+    inner: Arc<RwLock<AgentConfig>>,
+    // Path to the TOML config file watched for modification, if any.
+    source: Option<String>,
+}
+
+impl SharedConfig {
+    // Wrap an already-resolved config with no reloadable source.
+    pub fn new(config: AgentConfig) -> Self {
+        SharedConfig {
+            inner: Arc::new(RwLock::new(config)),
+            source: None,
+        }
+    }
+
+    // Wrap a config together with the config file it was loaded from, so later
+    // [`reload`] calls can re-read it.
+    pub fn with_source(config: AgentConfig, source: String) -> Self {
+        SharedConfig {
+            inner: Arc::new(RwLock::new(config)),
+            source: Some(source),
+        }
+    }
+
+    // The shared handle, for subsystems that need to read the live config.
+    pub fn handle(&self) -> Arc<RwLock<AgentConfig>> {
+        Arc::clone(&self.inner)
+    }
+
+    // Re-read the backing config file, parse it through the usual `from_str`
+    // path, and apply the mutable fields to the shared config in place.
+    pub fn reload(&self) -> Result<ReloadReport> {
+        let source = self
+            .source
+            .as_ref()
+            .ok_or_else(|| anyhow!("no config source to reload"))?;
+        let contents = fs::read_to_string(source)
+            .with_context(|| format!("Failed to read config file {}", source))?;
+        let new = AgentConfig::from_str_with_format(&contents, ConfigFormat::from_path(source))?;
+
+        let mut guard = self.inner.write().unwrap();
+        Ok(guard.apply_reload(&new))
+    }
+
+    // Apply a config parsed from a TOML string, for callers (and tests) that
+    // already hold the contents rather than a file path.
+    pub fn reload_from_str(&self, s: &str) -> Result<ReloadReport> {
+        let new = AgentConfig::from_str(s)?;
+        let mut guard = self.inner.write().unwrap();
+        Ok(guard.apply_reload(&new))
+    }
+
+    // inotify events that mean the backing file may have new contents. Many
+    // editors (and Kubernetes configmap projections) replace the file by
+    // rename rather than writing in place, so self-move/delete are watched too
+    // and the watch is re-armed on the fresh inode in [`ConfigWatcher::on_event`].
+    fn watch_mask() -> AddWatchFlags {
+        AddWatchFlags::IN_MODIFY
+            | AddWatchFlags::IN_CLOSE_WRITE
+            | AddWatchFlags::IN_MOVE_SELF
+            | AddWatchFlags::IN_DELETE_SELF
+    }
+
+    /// Begin watching the backing config file for modification.
+    ///
+    /// Returns a [`ConfigWatcher`] whose inotify file descriptor is exposed
+    /// via [`AsRawFd`] for registration in an event loop; when it becomes
+    /// readable, [`ConfigWatcher::on_event`] drains the queue and re-applies
+    /// the mutable fields. The watcher does not poll on its own. Fails if
+    /// there is no reloadable source.
+    pub fn watch(&self) -> Result<ConfigWatcher> {
+        let path = self
+            .source
+            .as_ref()
+            .ok_or_else(|| anyhow!("no config source to watch"))?
+            .clone();
+        let inotify = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)
+            .context("init inotify for config watch")?;
+        inotify
+            .add_watch(path.as_str(), Self::watch_mask())
+            .with_context(|| format!("watch config file {}", path))?;
+        Ok(ConfigWatcher {
+            inotify,
+            config: self.clone(),
+            path,
+        })
+    }
+}
+
+/// An inotify watch over a [`SharedConfig`]'s backing file.
+///
+/// Its [`AsRawFd`] can be registered in an event loop; on readability,
+/// [`on_event`](ConfigWatcher::on_event) re-applies any mutable fields that
+/// changed on disk.
+pub struct ConfigWatcher {
+    inotify: Inotify,
+    config: SharedConfig,
+    path: String,
+}
+
+impl ConfigWatcher {
+    /// Drain the pending inotify events and, if the file changed, reload the
+    /// shared config in place. Returns the [`ReloadReport`] when a reload ran,
+    /// or `None` when the readiness was spurious (no queued events). Re-arms the
+    /// watch if the file was replaced by a new inode — the common editor and
+    /// configmap save-by-rename case.
+    pub fn on_event(&self) -> Result<Option<ReloadReport>> {
+        let events = match self.inotify.read_events() {
+            Ok(events) => events,
+            // The fd can wake with nothing queued (edge-triggered races); that
+            // is not an error, just nothing to do yet.
+            Err(nix::errno::Errno::EAGAIN) => return Ok(None),
+            Err(e) => return Err(anyhow!("read config watch events: {}", e)),
+        };
+        if events.is_empty() {
+            return Ok(None);
+        }
+
+        // A rename/delete detaches the original watch (the kernel also emits
+        // IN_IGNORED), so re-arm on the path to keep tracking the replacement.
+        let detached = events.iter().any(|e| {
+            e.mask.intersects(
+                AddWatchFlags::IN_MOVE_SELF
+                    | AddWatchFlags::IN_DELETE_SELF
+                    | AddWatchFlags::IN_IGNORED,
+            )
+        });
+        if detached {
+            // Best effort: if the replacement is not in place yet, the next
+            // write will be missed, but a subsequent event re-arms again.
+            let _ = self.inotify.add_watch(self.path.as_str(), SharedConfig::watch_mask());
+        }
+
+        self.config.reload().map(Some)
+    }
+}
 
-            self.tracing = get_bool_value(&name_value).unwrap_or(false);
+impl AsRawFd for ConfigWatcher {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inotify.as_raw_fd()
+    }
+}
+
+// Extract the vsock port from a `server_addr`, if any.
+//
+// A scheme-less value (e.g. a bare hostname) is treated as carrying no port so
+// that non-network addresses pass through unchecked. A `vsock://<cid>:<port>`
+// address must have a numeric port; anything with an explicit but empty scheme
+// is rejected.
+#[instrument]
+fn parse_server_addr_port(addr: &str) -> Result<Option<i32>> {
+    let (scheme, rest) = match addr.split_once("://") {
+        Some(parts) => parts,
+        None => return Ok(None),
+    };
+
+    ensure!(!scheme.is_empty(), "missing scheme");
+
+    if scheme != "vsock" {
+        // Unix and other non-vsock addresses carry no vsock port to check.
+        return Ok(None);
+    }
+
+    match rest.rsplit_once(':') {
+        Some((_cid, port)) => {
+            let port = port
+                .parse::<i32>()
+                .with_context(|| format!("invalid vsock port {:?}", port))?;
+            Ok(Some(port))
         }
+        None => Ok(None),
     }
 }
 
@@ -487,33 +1636,145 @@ fn logrus_to_slog_level(logrus_level: &str) -> Result<slog::Level> {
         // Not in logrus
         "trace" => slog::Level::Trace,
 
-        _ => bail!(ERR_INVALID_LOG_LEVEL),
-    };
+        _ => bail!(ERR_INVALID_LOG_LEVEL),
+    };
+
+    Ok(level)
+}
+
+// Map an slog log level back to its logrus name, for config dumps that should
+// round-trip through `logrus_to_slog_level`.
+fn slog_level_to_logrus(level: slog::Level) -> &'static str {
+    match level {
+        slog::Level::Critical => "critical",
+        slog::Level::Error => "error",
+        slog::Level::Warning => "warn",
+        slog::Level::Info => "info",
+        slog::Level::Debug => "debug",
+        slog::Level::Trace => "trace",
+    }
+}
+
+#[instrument]
+fn get_log_level(param: &str) -> Result<slog::Level> {
+    let fields: Vec<&str> = param.split('=').collect();
+    ensure!(fields.len() == 2, ERR_INVALID_LOG_LEVEL_PARAM);
+    ensure!(fields[0] == LOG_LEVEL_OPTION, ERR_INVALID_LOG_LEVEL_KEY);
+
+    logrus_to_slog_level(fields[1])
+}
+
+// Parse the value of an `agent.log=` directive list into an optional default
+// level and a set of per-target rules. The default is `Some` only when a bare,
+// valid level is present so callers can leave an existing level untouched.
+//
+// Invalid level tokens are silently dropped, preserving the historical
+// "fall back to the default" behaviour.
+fn parse_log_directives(spec: &str) -> (Option<slog::Level>, Vec<(String, slog::Level)>) {
+    let mut default = None;
+    let mut rules: Vec<(String, slog::Level)> = Vec::new();
+
+    for token in spec.split(',') {
+        let token = token.trim();
+        if token.is_empty() {
+            continue;
+        }
+
+        match token.split_once('=') {
+            Some((target, level)) => {
+                if let Ok(level) = logrus_to_slog_level(level.trim()) {
+                    rules.push((target.trim().to_string(), level));
+                }
+            }
+            None => {
+                if let Ok(level) = logrus_to_slog_level(token) {
+                    default = Some(level);
+                }
+            }
+        }
+    }
+
+    // Most specific (longest) prefix first, so lookups can return on the first
+    // match.
+    rules.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+
+    (default, rules)
+}
+
+// Parse an `agent.log=<directives>` parameter, validating the key name and
+// returning the parsed default level and per-target rules.
+#[instrument]
+fn get_log_levels(param: &str) -> Result<(Option<slog::Level>, Vec<(String, slog::Level)>)> {
+    let fields: Vec<&str> = param.splitn(2, '=').collect();
+    ensure!(fields.len() == 2, ERR_INVALID_LOG_LEVEL_PARAM);
+    ensure!(fields[0] == LOG_LEVEL_OPTION, ERR_INVALID_LOG_LEVEL_KEY);
+
+    Ok(parse_log_directives(fields[1]))
+}
+
+#[instrument]
+fn get_timeout(param: &str) -> Result<time::Duration> {
+    let fields: Vec<&str> = param.split('=').collect();
+    ensure!(fields.len() == 2, ERR_INVALID_TIMEOUT);
+    ensure!(
+        matches!(fields[0], HOTPLUG_TIMOUT_OPTION | CDH_API_TIMOUT_OPTION),
+        ERR_INVALID_TIMEOUT_KEY
+    );
+
+    let value = fields[1]
+        .parse::<u64>()
+        .with_context(|| ERR_INVALID_TIMEOUT_PARAM)?;
+
+    Ok(time::Duration::from_secs(value))
+}
+
+#[instrument]
+fn get_config_version(param: &str) -> Result<u16> {
+    let fields: Vec<&str> = param.split('=').collect();
+    ensure!(fields.len() == 2, "invalid config version parameter");
+    ensure!(
+        fields[0] == CONFIG_VERSION_OPTION,
+        "invalid config version key name"
+    );
+
+    let value = fields[1]
+        .parse::<u16>()
+        .with_context(|| "unable to parse config version")?;
 
-    Ok(level)
+    Ok(value)
 }
 
 #[instrument]
-fn get_log_level(param: &str) -> Result<slog::Level> {
+fn get_retry_max_attempts(param: &str) -> Result<u32> {
     let fields: Vec<&str> = param.split('=').collect();
-    ensure!(fields.len() == 2, ERR_INVALID_LOG_LEVEL_PARAM);
-    ensure!(fields[0] == LOG_LEVEL_OPTION, ERR_INVALID_LOG_LEVEL_KEY);
+    ensure!(fields.len() == 2, ERR_INVALID_SERVER_RETRY);
+    ensure!(
+        fields[0] == SERVER_RETRY_MAX_ATTEMPTS_OPTION,
+        ERR_INVALID_SERVER_RETRY_KEY
+    );
 
-    logrus_to_slog_level(fields[1])
+    let value = fields[1]
+        .parse::<u32>()
+        .with_context(|| ERR_INVALID_SERVER_RETRY_PARAM)?;
+
+    Ok(value)
 }
 
 #[instrument]
-fn get_timeout(param: &str) -> Result<time::Duration> {
+fn get_retry_interval(param: &str) -> Result<time::Duration> {
     let fields: Vec<&str> = param.split('=').collect();
-    ensure!(fields.len() == 2, ERR_INVALID_TIMEOUT);
+    ensure!(fields.len() == 2, ERR_INVALID_SERVER_RETRY);
     ensure!(
-        matches!(fields[0], HOTPLUG_TIMOUT_OPTION | CDH_API_TIMOUT_OPTION),
-        ERR_INVALID_TIMEOUT_KEY
+        matches!(
+            fields[0],
+            SERVER_RETRY_INITIAL_INTERVAL_OPTION | SERVER_RETRY_MAX_INTERVAL_OPTION
+        ),
+        ERR_INVALID_SERVER_RETRY_KEY
     );
 
     let value = fields[1]
         .parse::<u64>()
-        .with_context(|| ERR_INVALID_TIMEOUT_PARAM)?;
+        .with_context(|| ERR_INVALID_SERVER_RETRY_PARAM)?;
 
     Ok(time::Duration::from_secs(value))
 }
@@ -594,6 +1855,382 @@ fn get_guest_components_features_value(param: &str) -> Result<GuestComponentsFea
         .map_err(|_| anyhow!(ERR_INVALID_GUEST_COMPONENTS_REST_API_VALUE))
 }
 
+#[cfg(feature = "guest-pull")]
+#[instrument]
+fn get_image_policy_value(param: &str) -> Result<Option<ImagePolicy>> {
+    let encoded = get_string_value(param)?;
+    Ok(Some(ImagePolicy::from_base64(&encoded)?))
+}
+
+// One entry in the single kernel-cmdline option registry. `from_cmdline`,
+// `builder_from_cmdline` and the collecting/strict error paths all drive off
+// this table, so every value-bearing `agent.*` option is defined exactly once
+// rather than being repeated across parallel per-key lists.
+struct CmdlineOption {
+    // The `agent.*` (or `systemd.*`) key this option is matched by.
+    key: &'static str,
+    // Parse a matching `key=value` token and fold it into the builder. Guard
+    // conditions (e.g. "positive port only") are applied by simply leaving the
+    // field unset when the value is rejected, preserving the default.
+    apply: fn(&str, &mut AgentConfigBuilder) -> Result<()>,
+}
+
+// The registry of every value-bearing cmdline option. Bare flags (which cannot
+// fail to parse) are matched directly in `builder_from_cmdline` and are
+// intentionally absent here.
+fn cmdline_options() -> Vec<CmdlineOption> {
+    macro_rules! opt {
+        ($key:expr, $apply:expr) => {
+            CmdlineOption {
+                key: $key,
+                apply: $apply,
+            }
+        };
+    }
+
+    #[allow(unused_mut)]
+    let mut options = vec![
+        opt!(LOG_LEVEL_OPTION, |p: &str, b: &mut AgentConfigBuilder| {
+            // Split the directive list into its bare default level and the
+            // per-target rules, layering them independently so both survive the
+            // resolve pipeline. Carry the default only when one is present (so a
+            // rules-only value doesn't clobber a weaker layer's default) and
+            // drop invalid tokens silently.
+            let (default, rules) = get_log_levels(p)?;
+            if let Some(level) = default {
+                b.log_level = Some(slog_level_to_logrus(level).to_string());
+            }
+            if !rules.is_empty() {
+                b.log_level_rules = Some(rules);
+            }
+            Ok(())
+        }),
+        opt!(CONFIG_VERSION_OPTION, |p: &str, b: &mut AgentConfigBuilder| {
+            b.config_version = Some(get_config_version(p)?);
+            Ok(())
+        }),
+        opt!(SERVER_ADDR_OPTION, |p: &str, b: &mut AgentConfigBuilder| {
+            b.server_addr = Some(get_string_value(p)?);
+            Ok(())
+        }),
+        opt!(
+            SERVER_RETRY_MAX_ATTEMPTS_OPTION,
+            |p: &str, b: &mut AgentConfigBuilder| {
+                b.server_retry_max_attempts = Some(get_retry_max_attempts(p)?);
+                Ok(())
+            }
+        ),
+        opt!(
+            SERVER_RETRY_INITIAL_INTERVAL_OPTION,
+            |p: &str, b: &mut AgentConfigBuilder| {
+                let v = get_retry_interval(p)?;
+                if v.as_secs() > 0 {
+                    b.server_retry_initial_interval = Some(v);
+                }
+                Ok(())
+            }
+        ),
+        opt!(
+            SERVER_RETRY_MAX_INTERVAL_OPTION,
+            |p: &str, b: &mut AgentConfigBuilder| {
+                let v = get_retry_interval(p)?;
+                if v.as_secs() > 0 {
+                    b.server_retry_max_interval = Some(v);
+                }
+                Ok(())
+            }
+        ),
+        opt!(HOTPLUG_TIMOUT_OPTION, |p: &str, b: &mut AgentConfigBuilder| {
+            let v = get_timeout(p)?;
+            if v.as_secs() > 0 {
+                b.hotplug_timeout = Some(v);
+            }
+            Ok(())
+        }),
+        opt!(CDH_API_TIMOUT_OPTION, |p: &str, b: &mut AgentConfigBuilder| {
+            let v = get_timeout(p)?;
+            if v.as_secs() > 0 {
+                b.cdh_api_timeout = Some(v);
+            }
+            Ok(())
+        }),
+        opt!(
+            DEBUG_CONSOLE_VPORT_OPTION,
+            |p: &str, b: &mut AgentConfigBuilder| {
+                let v = get_vsock_port(p)?;
+                if v > 0 {
+                    b.debug_console_vport = Some(v);
+                }
+                Ok(())
+            }
+        ),
+        opt!(LOG_VPORT_OPTION, |p: &str, b: &mut AgentConfigBuilder| {
+            let v = get_vsock_port(p)?;
+            if v > 0 {
+                b.log_vport = Some(v);
+            }
+            Ok(())
+        }),
+        opt!(PASSFD_LISTENER_PORT, |p: &str, b: &mut AgentConfigBuilder| {
+            let v = get_vsock_port(p)?;
+            if v > 0 {
+                b.passfd_listener_port = Some(v);
+            }
+            Ok(())
+        }),
+        opt!(
+            CONTAINER_PIPE_SIZE_OPTION,
+            |p: &str, b: &mut AgentConfigBuilder| {
+                b.container_pipe_size = Some(get_container_pipe_size(p)?);
+                Ok(())
+            }
+        ),
+        opt!(
+            UNIFIED_CGROUP_HIERARCHY_OPTION,
+            |p: &str, b: &mut AgentConfigBuilder| {
+                b.unified_cgroup_hierarchy = Some(get_bool_value(p)?);
+                Ok(())
+            }
+        ),
+        opt!(TRACE_MODE_OPTION, |p: &str, b: &mut AgentConfigBuilder| {
+            b.tracing = Some(get_bool_value(p)?);
+            Ok(())
+        }),
+        opt!(DUMP_CONFIG_OPTION, |p: &str, b: &mut AgentConfigBuilder| {
+            b.dump_config = Some(get_bool_value(p)?);
+            Ok(())
+        }),
+        opt!(
+            IMMEDIATE_SHUTDOWN_OPTION,
+            |p: &str, b: &mut AgentConfigBuilder| {
+                b.immediate_shutdown = Some(get_bool_value(p)?);
+                Ok(())
+            }
+        ),
+        opt!(HTTPS_PROXY, |p: &str, b: &mut AgentConfigBuilder| {
+            b.https_proxy = Some(get_url_value(p)?);
+            Ok(())
+        }),
+        opt!(NO_PROXY, |p: &str, b: &mut AgentConfigBuilder| {
+            b.no_proxy = Some(get_string_value(p)?);
+            Ok(())
+        }),
+        opt!(
+            GUEST_COMPONENTS_REST_API_OPTION,
+            |p: &str, b: &mut AgentConfigBuilder| {
+                b.guest_components_rest_api = Some(get_guest_components_features_value(p)?);
+                Ok(())
+            }
+        ),
+        opt!(
+            GUEST_COMPONENTS_PROCS_OPTION,
+            |p: &str, b: &mut AgentConfigBuilder| {
+                b.guest_components_procs = Some(get_guest_components_procs_value(p)?);
+                Ok(())
+            }
+        ),
+        opt!(
+            SECURE_STORAGE_INTEGRITY_OPTION,
+            |p: &str, b: &mut AgentConfigBuilder| {
+                b.secure_storage_integrity = Some(get_bool_value(p)?);
+                Ok(())
+            }
+        ),
+    ];
+
+    #[cfg(feature = "guest-pull")]
+    {
+        options.push(opt!(
+            IMAGE_REGISTRY_AUTH_OPTION,
+            |p: &str, b: &mut AgentConfigBuilder| {
+                b.image_registry_auth = Some(get_string_value(p)?);
+                Ok(())
+            }
+        ));
+        options.push(opt!(
+            ENABLE_SIGNATURE_VERIFICATION,
+            |p: &str, b: &mut AgentConfigBuilder| {
+                b.enable_signature_verification = Some(get_bool_value(p)?);
+                Ok(())
+            }
+        ));
+        options.push(opt!(
+            IMAGE_POLICY_FILE,
+            |p: &str, b: &mut AgentConfigBuilder| {
+                b.image_policy_file = Some(get_string_value(p)?);
+                Ok(())
+            }
+        ));
+        // The inline base64 policy is not a builder field (it is applied
+        // directly by `from_cmdline`), but it is validated here so the
+        // collecting and strict paths still reject a malformed or expired
+        // policy.
+        options.push(opt!(
+            IMAGE_POLICY_OPTION,
+            |p: &str, _b: &mut AgentConfigBuilder| { get_image_policy_value(p).map(|_| ()) }
+        ));
+    }
+
+    options
+}
+
+// Walk the whole cmdline, run every registered option parser against its
+// matching token, and collect a one-line message per failure. Each message
+// pairs the offending `key=value` token with its parser's fully-rendered cause
+// chain, so the order of the returned vector follows the order of the tokens.
+fn collect_parse_errors(cmdline: &str) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    for token in cmdline.split_ascii_whitespace() {
+        let key = token.split('=').next().unwrap_or(token);
+        // `cmdline_value_reason` is the single strict verdict for a `key=value`
+        // token, shared with `--config-check`, so the collecting boot path and
+        // the diagnostics report never disagree about the same cmdline.
+        if let Some(reason) = cmdline_value_reason(key, token) {
+            errors.push(format!("{}: {}", token, reason));
+        }
+    }
+
+    errors
+}
+
+// Fold per-token parse failures into a single error whose top-level message
+// summarises the count and whose `.chain()` yields each offending token in
+// order. Each link is a `key=value: <cause>` message, so iterating the chain
+// reproduces the list of concrete failures deterministically.
+fn combine_parse_errors(messages: Vec<String>) -> anyhow::Error {
+    let header = format!("{} invalid agent parameters", messages.len());
+
+    let mut iter = messages.into_iter().rev();
+    // `messages` is only ever passed in non-empty by the caller.
+    let mut err = anyhow!(iter.next().unwrap_or_default());
+    for msg in iter {
+        err = err.context(msg);
+    }
+
+    err.context(header)
+}
+
+// Scan a kernel cmdline (and the environment) for configuration mistakes that
+// would otherwise be silently ignored: unrecognized `agent.*` keys, values
+// that fail their parser, and unparsable env vars. Used by strict mode.
+fn collect_diagnostics(cmdline: &str) -> Vec<ConfigDiagnostic> {
+    let mut diags = Vec::new();
+
+    for token in cmdline.split_ascii_whitespace() {
+        if !token.starts_with("agent.") {
+            continue;
+        }
+
+        let key = token.split('=').next().unwrap_or(token);
+        let value = token.splitn(2, '=').nth(1).unwrap_or("").to_string();
+
+        if !KNOWN_CMDLINE_KEYS.contains(&key) {
+            diags.push(ConfigDiagnostic {
+                key: key.to_string(),
+                value,
+                reason: "unrecognized agent configuration key".to_string(),
+                source: DiagnosticSource::Cmdline,
+            });
+            continue;
+        }
+
+        if let Some(reason) = cmdline_value_reason(key, token) {
+            diags.push(ConfigDiagnostic {
+                key: key.to_string(),
+                value,
+                reason,
+                source: DiagnosticSource::Cmdline,
+            });
+        }
+    }
+
+    // Every `KATA_AGENT_*` variable silently falls back to its default on a bad
+    // value, so probe them all through the same per-key reasons used for the
+    // cmdline, attributing any failure to the environment.
+    for (var, key) in ENV_DIAGNOSTIC_KEYS {
+        if let Ok(value) = env::var(var) {
+            let token = format!("{}={}", key, value);
+            if let Some(reason) = cmdline_value_reason(key, &token) {
+                diags.push(ConfigDiagnostic {
+                    key: var.to_string(),
+                    value,
+                    reason,
+                    source: DiagnosticSource::Env,
+                });
+            }
+        }
+    }
+
+    diags
+}
+
+// Maps each `KATA_AGENT_*` variable that can silently fall back to a default on
+// a bad value to the `agent.*` key whose parser validates it, so strict mode
+// can reuse [`cmdline_value_reason`] to diagnose the environment too.
+const ENV_DIAGNOSTIC_KEYS: &[(&str, &str)] = &[
+    (LOG_LEVEL_ENV_VAR, LOG_LEVEL_OPTION),
+    (CONFIG_VERSION_ENV_VAR, CONFIG_VERSION_OPTION),
+    ("KATA_AGENT_HOTPLUG_TIMEOUT", HOTPLUG_TIMOUT_OPTION),
+    ("KATA_AGENT_CDH_API_TIMEOUT", CDH_API_TIMOUT_OPTION),
+    ("KATA_AGENT_CONTAINER_PIPE_SIZE", CONTAINER_PIPE_SIZE_OPTION),
+    ("KATA_AGENT_DEBUG_CONSOLE_VPORT", DEBUG_CONSOLE_VPORT_OPTION),
+    ("KATA_AGENT_LOG_VPORT", LOG_VPORT_OPTION),
+    ("KATA_AGENT_PASSFD_LISTENER_PORT", PASSFD_LISTENER_PORT),
+    ("KATA_AGENT_HTTPS_PROXY", HTTPS_PROXY),
+    (
+        "KATA_AGENT_GUEST_COMPONENTS_REST_API",
+        GUEST_COMPONENTS_REST_API_OPTION,
+    ),
+    (
+        "KATA_AGENT_GUEST_COMPONENTS_PROCS",
+        GUEST_COMPONENTS_PROCS_OPTION,
+    ),
+    (SERVER_RETRY_MAX_ATTEMPTS_ENV_VAR, SERVER_RETRY_MAX_ATTEMPTS_OPTION),
+    (
+        SERVER_RETRY_INITIAL_INTERVAL_ENV_VAR,
+        SERVER_RETRY_INITIAL_INTERVAL_OPTION,
+    ),
+    (SERVER_RETRY_MAX_INTERVAL_ENV_VAR, SERVER_RETRY_MAX_INTERVAL_OPTION),
+    ("KATA_AGENT_NO_PROXY", NO_PROXY),
+    #[cfg(feature = "guest-pull")]
+    ("KATA_AGENT_IMAGE_REGISTRY_AUTH", IMAGE_REGISTRY_AUTH_OPTION),
+    #[cfg(feature = "guest-pull")]
+    ("KATA_AGENT_IMAGE_POLICY_FILE", IMAGE_POLICY_FILE),
+];
+
+// Return a rejection reason for a known cmdline key whose value fails its
+// parser, or `None` when the value is acceptable.
+fn cmdline_value_reason(key: &str, token: &str) -> Option<String> {
+    // `agent.log` is deliberately lenient in the registry parser: a token with
+    // an invalid level is dropped (target prefixes are not checked) so a stale
+    // value can never stop the agent from booting. Strict mode is the one place that still rejects an
+    // outright invalid level, so that check lives here rather than in the shared
+    // parser.
+    if key == LOG_LEVEL_OPTION {
+        let value = token.splitn(2, '=').nth(1).unwrap_or("");
+        return log_directives_has_invalid(value).then(|| ERR_INVALID_LOG_LEVEL.to_string());
+    }
+
+    // Every other key is validated by the same registry closure that parses it
+    // for real, so the strict surfaces can never drift from the boot parser.
+    let options = cmdline_options();
+    let opt = options.iter().find(|o| o.key == key)?;
+    let mut sink = AgentConfigBuilder::empty();
+    (opt.apply)(token, &mut sink).err().map(|e| format!("{:#}", e))
+}
+
+// True if any non-empty token in a log-directive list carries an invalid level.
+fn log_directives_has_invalid(spec: &str) -> bool {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .any(|token| {
+            let level = token.split_once('=').map(|(_, l)| l.trim()).unwrap_or(token);
+            logrus_to_slog_level(level).is_err()
+        })
+}
+
 #[instrument]
 fn get_guest_components_procs_value(param: &str) -> Result<GuestComponentsProcs> {
     let fields: Vec<&str> = param.split('=').collect();
@@ -649,6 +2286,9 @@ mod tests {
             hotplug_timeout: time::Duration,
             container_pipe_size: i32,
             server_addr: &'a str,
+            server_retry_max_attempts: u32,
+            server_retry_initial_interval: time::Duration,
+            server_retry_max_interval: time::Duration,
             unified_cgroup_hierarchy: bool,
             tracing: bool,
             https_proxy: &'a str,
@@ -675,6 +2315,9 @@ mod tests {
                     hotplug_timeout: DEFAULT_HOTPLUG_TIMEOUT,
                     container_pipe_size: DEFAULT_CONTAINER_PIPE_SIZE,
                     server_addr: TEST_SERVER_ADDR,
+                    server_retry_max_attempts: DEFAULT_SERVER_RETRY_MAX_ATTEMPTS,
+                    server_retry_initial_interval: DEFAULT_SERVER_RETRY_INITIAL_INTERVAL,
+                    server_retry_max_interval: DEFAULT_SERVER_RETRY_MAX_INTERVAL,
                     unified_cgroup_hierarchy: false,
                     tracing: false,
                     https_proxy: "",
@@ -951,6 +2594,42 @@ mod tests {
                 server_addr: "unix:///tmp/foo.socket",
                 ..Default::default()
             },
+            // Server reconnect backoff tuning.
+            TestData {
+                contents: "agent.server_retry_max_attempts=5",
+                server_retry_max_attempts: 5,
+                ..Default::default()
+            },
+            TestData {
+                contents: "agent.server_retry_max_attempts=0",
+                server_retry_max_attempts: 0,
+                ..Default::default()
+            },
+            TestData {
+                contents: "agent.server_retry_initial_interval=2 agent.server_retry_max_interval=60",
+                server_retry_initial_interval: time::Duration::from_secs(2),
+                server_retry_max_interval: time::Duration::from_secs(60),
+                ..Default::default()
+            },
+            // A zero interval is ignored, mirroring hotplug_timeout.
+            TestData {
+                contents: "agent.server_retry_initial_interval=0",
+                server_retry_initial_interval: DEFAULT_SERVER_RETRY_INITIAL_INTERVAL,
+                ..Default::default()
+            },
+            // env vars take precedence over the kernel cmdline.
+            TestData {
+                contents: "agent.server_retry_max_attempts=3",
+                env_vars: vec!["KATA_AGENT_SERVER_RETRY_MAX_ATTEMPTS=9"],
+                server_retry_max_attempts: 9,
+                ..Default::default()
+            },
+            TestData {
+                contents: "",
+                env_vars: vec!["KATA_AGENT_SERVER_RETRY_INITIAL_INTERVAL=7"],
+                server_retry_initial_interval: time::Duration::from_secs(7),
+                ..Default::default()
+            },
             TestData {
                 contents: "trace",
                 tracing: false,
@@ -1220,6 +2899,21 @@ mod tests {
             assert_eq!(d.hotplug_timeout, config.hotplug_timeout, "{}", msg);
             assert_eq!(d.container_pipe_size, config.container_pipe_size, "{}", msg);
             assert_eq!(d.server_addr, config.server_addr, "{}", msg);
+            assert_eq!(
+                d.server_retry_max_attempts, config.server_retry_max_attempts,
+                "{}",
+                msg
+            );
+            assert_eq!(
+                d.server_retry_initial_interval, config.server_retry_initial_interval,
+                "{}",
+                msg
+            );
+            assert_eq!(
+                d.server_retry_max_interval, config.server_retry_max_interval,
+                "{}",
+                msg
+            );
             assert_eq!(d.tracing, config.tracing, "{}", msg);
             assert_eq!(d.https_proxy, config.https_proxy, "{}", msg);
             assert_eq!(d.no_proxy, config.no_proxy, "{}", msg);
@@ -1261,11 +2955,12 @@ mod tests {
         let expected = AgentConfig {
             dev_mode: true,
             server_addr: "unix:///tmp/overwrite.socket".to_string(),
+            config_version: 1,
             ..Default::default()
         };
 
         let example_config_file_contents =
-            "dev_mode = true\nserver_addr = 'unix:///tmp/ignored.socket'";
+            "dev_mode = true\nserver_addr = 'unix:///tmp/ignored.socket'\nconfig_version = 2\ncdh_api_timeout = 9";
         let dir = tempdir().expect("failed to create tmpdir");
         let file_path = dir.path().join("config.toml");
         let filename = file_path.to_str().expect("failed to create filename");
@@ -1275,12 +2970,14 @@ mod tests {
 
         // Ensure that the env has precedence over agent config file
         env::set_var("KATA_AGENT_SERVER_ADDR", "unix:///tmp/overwrite.socket");
+        env::set_var("KATA_AGENT_CONFIG_VERSION", "1");
 
         let config =
             AgentConfig::from_cmdline("", vec!["--config".to_string(), filename.to_string()])
                 .expect("Failed to parse command line");
 
         env::remove_var("KATA_AGENT_SERVER_ADDR");
+        env::remove_var("KATA_AGENT_CONFIG_VERSION");
 
         assert_eq!(expected.debug_console, config.debug_console);
         assert_eq!(expected.dev_mode, config.dev_mode);
@@ -1293,6 +2990,414 @@ mod tests {
         assert_eq!(expected.container_pipe_size, config.container_pipe_size);
         assert_eq!(expected.server_addr, config.server_addr);
         assert_eq!(expected.tracing, config.tracing);
+        assert_eq!(expected.config_version, config.config_version);
+        // The env forced config_version back to 1, which predates
+        // cdh_api_timeout: re-gating must have dropped the file's `9` back to
+        // the default rather than leaving it honoured under an older version.
+        assert!(!config.supports_cdh_api_timeout());
+        assert_eq!(config.cdh_api_timeout, DEFAULT_CDH_API_TIMEOUT);
+    }
+
+    #[test]
+    #[serial]
+    fn test_resolve_precedence() {
+        let dir = tempdir().expect("failed to create tmpdir");
+
+        let file_path = dir.path().join("agent.toml");
+        let mut file = File::create(&file_path).unwrap();
+        file.write_all(b"server_addr = 'unix:///from/file.socket'\ndev_mode = true\n")
+            .unwrap();
+
+        let cmdline_path = dir.path().join("cmdline");
+        let mut cmdline = File::create(&cmdline_path).unwrap();
+        cmdline
+            .write_all(b"agent.server_addr=unix:///from/cmdline.socket agent.log=debug,rustjail=trace")
+            .unwrap();
+
+        env::set_var("KATA_AGENT_SERVER_ADDR", "unix:///from/env.socket");
+
+        let sources = vec![
+            ConfigSource::Defaults,
+            ConfigSource::File(file_path.to_str().unwrap().to_string()),
+            ConfigSource::Cmdline(cmdline_path.to_str().unwrap().to_string()),
+            ConfigSource::Env,
+        ];
+
+        let (config, prov) = AgentConfig::resolve(&sources).expect("resolve failed");
+
+        env::remove_var("KATA_AGENT_SERVER_ADDR");
+
+        // Env wins server_addr, cmdline wins log_level, file wins dev_mode.
+        assert_eq!(config.server_addr, "unix:///from/env.socket");
+        assert_eq!(config.log_level, slog::Level::Debug);
+        // Per-target directives flow through the layered pipeline, not just the
+        // bare default level.
+        assert_eq!(
+            config.log_level_rules,
+            vec![("rustjail".to_string(), slog::Level::Trace)]
+        );
+        assert_eq!(config.log_level_for("rustjail::mount"), slog::Level::Trace);
+        assert!(config.dev_mode);
+
+        assert_eq!(prov.get("server_addr"), Some(&"env"));
+        assert_eq!(prov.get("log_level"), Some(&"cmdline"));
+        assert_eq!(prov.get("dev_mode"), Some(&"file"));
+        // A field nobody set keeps its default and has no provenance entry.
+        assert_eq!(prov.get("hotplug_timeout"), None);
+        assert_eq!(config.hotplug_timeout, DEFAULT_HOTPLUG_TIMEOUT);
+    }
+
+    #[test]
+    #[serial]
+    fn test_dump_config_and_immediate_shutdown() {
+        let dir = tempdir().expect("failed to create tmpdir");
+        let file_path = dir.path().join("cmdline");
+        let filename = file_path.to_str().unwrap();
+
+        // Bare cmdline flags enable both modes.
+        let mut file = File::create(filename).unwrap();
+        file.write_all(b"agent.dump_config agent.immediate_shutdown")
+            .unwrap();
+        let config = AgentConfig::from_cmdline(filename, vec![]).unwrap();
+        assert!(config.dump_config);
+        assert!(config.immediate_shutdown);
+
+        // The --dump-config CLI arg forces dump mode on its own.
+        let mut file = File::create(filename).unwrap();
+        file.write_all(b"").unwrap();
+        let config =
+            AgentConfig::from_cmdline(filename, vec!["--dump-config".to_string()]).unwrap();
+        assert!(config.dump_config);
+        assert!(!config.immediate_shutdown);
+
+        // KATA_AGENT_DUMP_CONFIG enables dump mode via the environment.
+        env::set_var("KATA_AGENT_DUMP_CONFIG", "true");
+        let config = AgentConfig::from_cmdline(filename, vec![]).unwrap();
+        env::remove_var("KATA_AGENT_DUMP_CONFIG");
+        assert!(config.dump_config);
+
+        // The TOML dump round-trips back through the parser, including the
+        // per-target log rules folded into the `log_level` directive.
+        let mut file = File::create(filename).unwrap();
+        file.write_all(b"agent.log=info,rustjail=debug agent.dump_config")
+            .unwrap();
+        let config = AgentConfig::from_cmdline(filename, vec![]).unwrap();
+        let dumped = config.to_toml().unwrap();
+        let reparsed = AgentConfig::from_str(&dumped).unwrap();
+        assert_eq!(reparsed.log_level, config.log_level);
+        assert_eq!(reparsed.server_addr, config.server_addr);
+        assert_eq!(reparsed.log_level_rules, config.log_level_rules);
+        assert_eq!(
+            reparsed.log_level_rules,
+            vec![("rustjail".to_string(), slog::Level::Debug)]
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_log_level_directives() {
+        let dir = tempdir().expect("failed to create tmpdir");
+        let file_path = dir.path().join("cmdline");
+        let filename = file_path.to_str().unwrap();
+
+        let mut file = File::create(filename).unwrap();
+        file.write_all(b"agent.log=info,rustjail=debug,netlink=trace")
+            .unwrap();
+        let config = AgentConfig::from_cmdline(filename, vec![]).unwrap();
+
+        assert_eq!(config.log_level, slog::Level::Info);
+        // Rules are sorted most-specific first.
+        assert_eq!(
+            config.log_level_rules,
+            vec![
+                ("rustjail".to_string(), slog::Level::Debug),
+                ("netlink".to_string(), slog::Level::Trace),
+            ]
+        );
+        assert_eq!(config.log_level_for("rustjail::cgroups"), slog::Level::Debug);
+        assert_eq!(config.log_level_for("netlink"), slog::Level::Trace);
+        assert_eq!(config.log_level_for("something-else"), slog::Level::Info);
+
+        // Backward compatibility: a lone level still means "that level
+        // everywhere".
+        let mut file = File::create(filename).unwrap();
+        file.write_all(b"agent.log=debug").unwrap();
+        let config = AgentConfig::from_cmdline(filename, vec![]).unwrap();
+        assert_eq!(config.log_level, slog::Level::Debug);
+        assert!(config.log_level_rules.is_empty());
+        assert_eq!(config.log_level_for("anything"), slog::Level::Debug);
+
+        // Invalid level tokens are dropped, leaving the default in place.
+        let mut file = File::create(filename).unwrap();
+        file.write_all(b"agent.log=bogus,rustjail=alsobogus").unwrap();
+        let config = AgentConfig::from_cmdline(filename, vec![]).unwrap();
+        assert_eq!(config.log_level, DEFAULT_LOG_LEVEL);
+        assert!(config.log_level_rules.is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn test_config_strict_diagnostics() {
+        let dir = tempdir().expect("failed to create tmpdir");
+        let file_path = dir.path().join("cmdline");
+        let filename = file_path.to_str().unwrap();
+
+        // Without strict mode the dubious input is tolerated as before.
+        let mut file = File::create(filename).unwrap();
+        file.write_all(b"agent.devmode agent.container_pip_siz=100")
+            .unwrap();
+        let config = AgentConfig::from_cmdline(filename, vec![]).unwrap();
+        assert!(config.dev_mode);
+
+        // Strict mode rejects an unrecognized key...
+        let mut file = File::create(filename).unwrap();
+        file.write_all(b"agent.config_strict agent.container_pip_siz=100")
+            .unwrap();
+        assert!(AgentConfig::from_cmdline(filename, vec![]).is_err());
+
+        // ...and an out-of-range known value.
+        let mut file = File::create(filename).unwrap();
+        file.write_all(b"agent.hotplug_timeout=foo").unwrap();
+        assert!(
+            AgentConfig::from_cmdline(filename, vec!["--config-check".to_string()]).is_err()
+        );
+
+        // A clean cmdline passes strict mode.
+        let mut file = File::create(filename).unwrap();
+        file.write_all(b"agent.config_strict agent.hotplug_timeout=5")
+            .unwrap();
+        assert!(AgentConfig::from_cmdline(filename, vec![]).is_ok());
+
+        // The collector surfaces structured diagnostics directly.
+        let diags = collect_diagnostics("agent.unknown_key=1 agent.log=bogus");
+        assert_eq!(diags.len(), 2);
+        assert!(diags.iter().any(|d| d.key == "agent.unknown_key"
+            && d.source == DiagnosticSource::Cmdline));
+        assert!(diags.iter().any(|d| d.key == LOG_LEVEL_OPTION));
+
+        // A bad value in any KATA_AGENT_* variable is attributed to the
+        // environment, not just the log level.
+        env::set_var("KATA_AGENT_CONTAINER_PIPE_SIZE", "abc");
+        let diags = collect_diagnostics("");
+        env::remove_var("KATA_AGENT_CONTAINER_PIPE_SIZE");
+        assert!(diags.iter().any(|d| d.key == "KATA_AGENT_CONTAINER_PIPE_SIZE"
+            && d.source == DiagnosticSource::Env));
+    }
+
+    #[test]
+    #[serial]
+    fn test_from_cmdline_collect() {
+        let dir = tempdir().expect("failed to create tmpdir");
+        let file_path = dir.path().join("cmdline");
+        let filename = file_path.to_str().unwrap();
+
+        // A clean cmdline parses exactly as `from_cmdline` would.
+        let mut file = File::create(filename).unwrap();
+        file.write_all(b"agent.devmode agent.hotplug_timeout=5")
+            .unwrap();
+        let config = AgentConfig::from_cmdline_collect(filename, vec![]).unwrap();
+        assert!(config.dev_mode);
+        assert_eq!(config.hotplug_timeout, time::Duration::from_secs(5));
+
+        // Several malformed tokens are reported together, in cmdline order, each
+        // as its own link in the error chain beneath a counting header.
+        let mut file = File::create(filename).unwrap();
+        file.write_all(b"agent.hotplug_timeout=foo agent.container_pipe_size=-1 agent.log_vport=bar")
+            .unwrap();
+        let err = AgentConfig::from_cmdline_collect(filename, vec![]).unwrap_err();
+
+        let chain: Vec<String> = err.chain().map(|c| c.to_string()).collect();
+        assert_eq!(chain.len(), 4);
+        assert_eq!(chain[0], "3 invalid agent parameters");
+        assert!(chain[1].starts_with("agent.hotplug_timeout=foo:"));
+        assert!(chain[2].starts_with("agent.container_pipe_size=-1:"));
+        assert!(chain[3].starts_with("agent.log_vport=bar:"));
+
+        // The individual cause is preserved in each link.
+        assert!(chain[1].contains("invalid digit found in string"));
+        assert!(chain[2].contains(ERR_INVALID_CONTAINER_PIPE_NEGATIVE));
+
+        // An outright invalid log level is rejected here too, matching the
+        // `--config-check` diagnostics rather than being silently dropped.
+        let mut file = File::create(filename).unwrap();
+        file.write_all(b"agent.log=bogus").unwrap();
+        let err = AgentConfig::from_cmdline_collect(filename, vec![]).unwrap_err();
+        assert!(err.chain().any(|c| c.to_string().contains(ERR_INVALID_LOG_LEVEL)));
+    }
+
+    #[test]
+    #[serial]
+    fn test_config_version_gating() {
+        let dir = tempdir().expect("failed to create tmpdir");
+        let file_path = dir.path().join("cmdline");
+        let filename = file_path.to_str().unwrap();
+
+        // A v1 agent tolerates options introduced in v2 but degrades them to
+        // their defaults instead of honouring a peer-of-v2 value.
+        let mut file = File::create(filename).unwrap();
+        file.write_all(b"agent.config_version=1 agent.guest_components_procs=none agent.cdh_api_timeout=9")
+            .unwrap();
+        let config = AgentConfig::from_cmdline(filename, vec![]).unwrap();
+        assert_eq!(config.config_version, 1);
+        assert!(!config.supports_cdh_api_timeout());
+        assert!(!config.supports_guest_components_rest_api());
+        assert_eq!(
+            config.guest_components_procs,
+            GuestComponentsProcs::default()
+        );
+        assert_eq!(config.cdh_api_timeout, DEFAULT_CDH_API_TIMEOUT);
+
+        // At the introducing version the same options are honoured.
+        let mut file = File::create(filename).unwrap();
+        file.write_all(b"agent.config_version=2 agent.guest_components_procs=none agent.cdh_api_timeout=9")
+            .unwrap();
+        let config = AgentConfig::from_cmdline(filename, vec![]).unwrap();
+        assert!(config.supports_cdh_api_timeout());
+        assert!(config.supports_guest_components_rest_api());
+        assert_eq!(config.guest_components_procs, GuestComponentsProcs::None);
+        assert_eq!(config.cdh_api_timeout, time::Duration::from_secs(9));
+
+        // The default (no version specified) is the agent's native version.
+        let mut file = File::create(filename).unwrap();
+        file.write_all(b"agent.devmode").unwrap();
+        let config = AgentConfig::from_cmdline(filename, vec![]).unwrap();
+        assert_eq!(config.config_version, DEFAULT_CONFIG_VERSION);
+    }
+
+    #[test]
+    fn test_reload() {
+        // apply_reload swaps the mutable fields and reports immutable diffs.
+        let mut current = AgentConfig::default();
+        let new = AgentConfig {
+            log_level: slog::Level::Trace,
+            hotplug_timeout: time::Duration::from_secs(7),
+            cdh_api_timeout: time::Duration::from_secs(12),
+            server_addr: "unix:///tmp/new.socket".to_string(),
+            ..Default::default()
+        };
+
+        let report = current.apply_reload(&new);
+        assert_eq!(current.log_level, slog::Level::Trace);
+        assert_eq!(current.hotplug_timeout, time::Duration::from_secs(7));
+        assert_eq!(current.cdh_api_timeout, time::Duration::from_secs(12));
+
+        // server_addr is immutable: reported and left untouched.
+        assert!(report.requires_restart.contains(&"server_addr"));
+        assert_ne!(current.server_addr, new.server_addr);
+
+        // The shared handle re-parses a TOML string and applies it live.
+        let shared = SharedConfig::new(AgentConfig::default());
+        let report = shared
+            .reload_from_str("log_level = 'debug'")
+            .expect("reload failed");
+        assert!(report.requires_restart.is_empty());
+
+        let handle = shared.handle();
+        let guard = handle.read().unwrap();
+        assert_eq!(guard.log_level, slog::Level::Debug);
+
+        // Successive reloads swap in each new level and timeout in turn; the
+        // timeout is written as bare seconds, the way a config file expresses it.
+        drop(guard);
+        shared
+            .reload_from_str("log_level = 'trace'\nhotplug_timeout = 9")
+            .expect("reload failed");
+        let handle = shared.handle();
+        let guard = handle.read().unwrap();
+        assert_eq!(guard.log_level, slog::Level::Trace);
+        assert_eq!(guard.hotplug_timeout, time::Duration::from_secs(9));
+    }
+
+    #[test]
+    fn test_watch() {
+        // Watching requires a backing file; a sourceless config cannot watch.
+        let orphan = SharedConfig::new(AgentConfig::default());
+        assert!(orphan.watch().is_err());
+
+        let dir = tempdir().expect("failed to create tmpdir");
+        let file_path = dir.path().join("agent.toml");
+        let filename = file_path.to_str().unwrap().to_string();
+        File::create(&file_path)
+            .unwrap()
+            .write_all(b"log_level = 'info'\n")
+            .unwrap();
+
+        let shared = SharedConfig::with_source(AgentConfig::default(), filename.clone());
+        let watcher = shared.watch().expect("failed to start config watch");
+        // The inotify fd must be registerable in an event loop.
+        assert!(watcher.as_raw_fd() >= 0);
+
+        // A write to the file wakes the watcher, which re-applies the new level.
+        File::create(&file_path)
+            .unwrap()
+            .write_all(b"log_level = 'debug'\n")
+            .unwrap();
+
+        // inotify delivery is effectively synchronous with the write, but allow
+        // a few spurious-empty reads so the test stays robust under load.
+        let mut report = None;
+        for _ in 0..50 {
+            match watcher.on_event().expect("on_event failed") {
+                Some(r) => {
+                    report = Some(r);
+                    break;
+                }
+                None => std::thread::sleep(time::Duration::from_millis(10)),
+            }
+        }
+        assert!(report.is_some(), "watcher never observed the file change");
+
+        let handle = shared.handle();
+        let guard = handle.read().unwrap();
+        assert_eq!(guard.log_level, slog::Level::Debug);
+    }
+
+    #[test]
+    fn test_validate() {
+        // A default configuration is internally consistent.
+        let config: AgentConfig = Default::default();
+        assert!(config.validate().is_ok());
+
+        // Colliding vports are rejected.
+        let bad = AgentConfig {
+            debug_console_vport: 1234,
+            log_vport: 1234,
+            ..Default::default()
+        };
+        assert!(bad.validate().is_err());
+
+        // A passfd listener colliding with the server_addr vsock port is
+        // rejected.
+        let bad = AgentConfig {
+            server_addr: "vsock://-1:1024".to_string(),
+            passfd_listener_port: 1024,
+            ..Default::default()
+        };
+        assert!(bad.validate().is_err());
+
+        // A malformed vsock port is rejected.
+        let bad = AgentConfig {
+            server_addr: "vsock://-1:notaport".to_string(),
+            ..Default::default()
+        };
+        assert!(bad.validate().is_err());
+
+        // Requesting the attestation REST surface without the api-server-rest
+        // process can never be satisfied.
+        let bad = AgentConfig {
+            guest_components_rest_api: GuestComponentsFeatures::Attestation,
+            guest_components_procs: GuestComponentsProcs::None,
+            ..Default::default()
+        };
+        assert!(bad.validate().is_err());
+
+        // The benign `resource` default is fine alongside any procs value.
+        let ok = AgentConfig {
+            guest_components_rest_api: GuestComponentsFeatures::Resource,
+            guest_components_procs: GuestComponentsProcs::None,
+            ..Default::default()
+        };
+        assert!(ok.validate().is_ok());
     }
 
     #[test]
@@ -1853,6 +3958,68 @@ Caused by:
         }
     }
 
+    #[cfg(feature = "guest-pull")]
+    #[test]
+    fn test_get_image_policy_value() {
+        let encode = |doc: &str| base64::engine::general_purpose::STANDARD.encode(doc);
+
+        // A far-future expiration with one condition of each supported type.
+        let doc = r#"{
+            "expiration": 9999999999,
+            "conditions": [
+                ["eq", "$registry", "docker.io"],
+                ["starts-with", "$image", "quay.io/"],
+                ["content-length-range", 0, 1048576]
+            ]
+        }"#;
+        let param = format!("{}={}", IMAGE_POLICY_OPTION, encode(doc));
+        let policy = get_image_policy_value(&param)
+            .expect("valid policy")
+            .expect("policy present");
+        assert_eq!(
+            policy.conditions,
+            vec![
+                PolicyCondition::Eq {
+                    field: "$registry".to_string(),
+                    value: "docker.io".to_string(),
+                },
+                PolicyCondition::StartsWith {
+                    field: "$image".to_string(),
+                    value: "quay.io/".to_string(),
+                },
+                PolicyCondition::ContentLengthRange {
+                    min: 0,
+                    max: 1048576,
+                },
+            ]
+        );
+
+        // An expired policy is rejected.
+        let expired = encode(r#"{"expiration": 0, "conditions": []}"#);
+        let param = format!("{}={}", IMAGE_POLICY_OPTION, expired);
+        assert!(get_image_policy_value(&param).is_err());
+
+        // A negative expiration must not wrap around to a huge unsigned value
+        // and be treated as not-yet-expired; it is rejected like any other
+        // expired policy.
+        let negative = encode(r#"{"expiration": -1, "conditions": []}"#);
+        let param = format!("{}={}", IMAGE_POLICY_OPTION, negative);
+        assert!(get_image_policy_value(&param).is_err());
+
+        // An unknown condition verb is rejected.
+        let bad_verb = encode(r#"{"expiration": 9999999999, "conditions": [["contains", "$x", "y"]]}"#);
+        let param = format!("{}={}", IMAGE_POLICY_OPTION, bad_verb);
+        assert!(get_image_policy_value(&param).is_err());
+
+        // Malformed base64 is rejected.
+        let param = format!("{}=not valid base64!", IMAGE_POLICY_OPTION);
+        assert!(get_image_policy_value(&param).is_err());
+
+        // Valid base64 that is not valid JSON is rejected.
+        let param = format!("{}={}", IMAGE_POLICY_OPTION, encode("not json"));
+        assert!(get_image_policy_value(&param).is_err());
+    }
+
     #[test]
     fn test_config_builder_from_string() {
         let config = AgentConfig::from_str(
@@ -1880,4 +4047,84 @@ Caused by:
         // Verify that the default values are valid
         assert_eq!(config.hotplug_timeout, DEFAULT_HOTPLUG_TIMEOUT);
     }
+
+    #[test]
+    fn test_config_format_from_path() {
+        assert_eq!(ConfigFormat::from_path("/etc/kata/agent.toml"), ConfigFormat::Toml);
+        assert_eq!(ConfigFormat::from_path("/etc/kata/agent.yaml"), ConfigFormat::Yaml);
+        assert_eq!(ConfigFormat::from_path("/etc/kata/agent.yml"), ConfigFormat::Yaml);
+        assert_eq!(ConfigFormat::from_path("/etc/kata/agent.JSON"), ConfigFormat::Json);
+        // Unknown or absent extensions keep the historical TOML behaviour.
+        assert_eq!(ConfigFormat::from_path("/etc/kata/agent.conf"), ConfigFormat::Toml);
+        assert_eq!(ConfigFormat::from_path("agent"), ConfigFormat::Toml);
+    }
+
+    #[test]
+    fn test_config_builder_format_round_trip() {
+        let toml = AgentConfig::from_str_with_format(
+            r#"
+               dev_mode = true
+               server_addr = 'vsock://8:2048'
+               guest_components_procs = "api-server-rest"
+               guest_components_rest_api = "all"
+              "#,
+            ConfigFormat::Toml,
+        )
+        .unwrap();
+
+        let yaml = AgentConfig::from_str_with_format(
+            "dev_mode: true\nserver_addr: 'vsock://8:2048'\nguest_components_procs: api-server-rest\nguest_components_rest_api: all\n",
+            ConfigFormat::Yaml,
+        )
+        .unwrap();
+
+        let json = AgentConfig::from_str_with_format(
+            r#"{
+                "dev_mode": true,
+                "server_addr": "vsock://8:2048",
+                "guest_components_procs": "api-server-rest",
+                "guest_components_rest_api": "all"
+              }"#,
+            ConfigFormat::Json,
+        )
+        .unwrap();
+
+        // Each format must produce an identical resolved configuration.
+        for config in [&toml, &yaml, &json] {
+            assert!(config.dev_mode);
+            assert_eq!(config.server_addr, "vsock://8:2048");
+            assert_eq!(config.guest_components_procs, GuestComponentsProcs::ApiServerRest);
+            assert_eq!(config.guest_components_rest_api, GuestComponentsFeatures::All);
+        }
+    }
+
+    #[test]
+    fn test_server_retry_backoff() {
+        // The schedule doubles each interval towards the ceiling, caps there,
+        // and stops once the attempt limit is hit.
+        let config = AgentConfig {
+            server_retry_max_attempts: 5,
+            server_retry_initial_interval: time::Duration::from_secs(1),
+            server_retry_max_interval: time::Duration::from_secs(8),
+            ..Default::default()
+        };
+        let schedule: Vec<u64> = config.server_retry_backoff().map(|d| d.as_secs()).collect();
+        // 1, 2, 4, then saturated at the 8s cap for the remaining attempts.
+        assert_eq!(schedule, vec![1, 2, 4, 8, 8]);
+
+        // `0` attempts means retry forever, so the iterator never terminates:
+        // sample a prefix and confirm it has settled at the cap.
+        let forever = AgentConfig {
+            server_retry_max_attempts: 0,
+            server_retry_initial_interval: time::Duration::from_secs(1),
+            server_retry_max_interval: time::Duration::from_secs(4),
+            ..Default::default()
+        };
+        let prefix: Vec<u64> = forever
+            .server_retry_backoff()
+            .take(6)
+            .map(|d| d.as_secs())
+            .collect();
+        assert_eq!(prefix, vec![1, 2, 4, 4, 4, 4]);
+    }
 }